@@ -1,6 +1,10 @@
 pub const VERSION: &str = "0.0.1";
 
-use ast::{Deq, Ident, RecordData, Term, Term1, TextEntry, TypeEnumData, Val};
+use std::borrow::Cow;
+use ast::{
+    Deq, FilePrefix, Ident, Import, ImportMode, ImportTarget, RecordData, Term, Term1, TextEntry,
+    TypeEnumData, Url, Val,
+};
 
 pub mod new {
     use super::*;
@@ -19,10 +23,13 @@ pub mod new {
 
     pub mod term {
         use super::*;
-        impl_report! {
-        var -> Term 's
-            : (&'s str, &'s str)
-            = |(name, scope)| Term::Var(name, u16::from_str_radix(scope, 10).unwrap()) }
+        /// A variable, or the reason its index is invalid.
+        pub fn var<'s>((name, scope): (&'s str, &'s str)) -> Result<Term<'s>, String> {
+            let idx = scope.parse().map_err(|_| format!("invalid variable index: {}@{}", name, scope))?;
+            let t = Term::Var(name, idx);
+            log::trace!("Reduce {:?}", t);
+            Ok(t)
+        }
 
         impl_report! {
         field_access -> Term 's
@@ -54,21 +61,50 @@ pub mod new {
             : Val<'s>
             = Term::Expr }
 
+        /// A text literal, or the reason it is invalid.
+        pub fn text<'s>((s, t): (u8, Deq<TextEntry<'s>>)) -> Result<Term<'s>, String> {
+            let t = t
+                .into_iter()
+                .map(|(raw, v)| Ok((unescape(s, raw)?, v)))
+                .collect::<Result<_, String>>()?;
+            let t = match s {
+                2 => Term::Text(s, dedent(t)),
+                _ => Term::Text(s, t),
+            };
+            log::trace!("Reduce {:?}", t);
+            Ok(t)
+        }
+
         impl_report! {
-        text -> Term 's
-            : (u8, Deq<TextEntry<'s>>)
-            = |(s, t)| Term::Text(s, t) }
+        natural -> Term 's
+            : &'s str
+            = |s: &'s str| match s.strip_prefix('+') {
+                Some(s) => Term::Integer(s.parse().unwrap()),
+                None => Term::Natural(s.parse().unwrap()),
+            } }
 
         impl_report! {
         integer -> Term 's
-            : (bool, &'s str)
-            = |(is_neg, val)| Term::Integer(if is_neg { -1 } else { 1 } * i32::from_str_radix(val, 10).unwrap()) }
+            : &'s str
+            = |s: &'s str| Term::Integer(s.parse().unwrap()) }
 
         impl_report! {
         double -> Term 's
             : &'s str
             = |s: &'s str| Term::Double(s.parse().unwrap()) }
 
+        impl_report! {
+        bytes -> Term 's
+            : &'s str
+            = |s: &'s str| {
+                let hex = s.trim_start_matches("0x").trim_matches('"');
+                let bytes = (0..hex.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+                    .collect();
+                Term::Bytes(bytes)
+            } }
+
         impl_report! {
         project -> Term 's
             : (Box<Term<'s>>, Deq<Term1<'s>>)
@@ -81,10 +117,289 @@ pub mod new {
 
         impl_report! {
         import -> Term 's
-            : (&'s str, Option<&'s str>, Option<&'s str>, Option<(&'s str, Option<&'s str>)>)
-            = |(path, guard, as_, fall)| Term::Import { path, guard, as_, fall } }
+            : Box<Import<'s>>
+            = Term::Import }
+
+        /// Desugar dotted fields into nested records. Repeated fields are
+        /// combined with `∧`.
+        pub fn nest_fields(fields: RecordData) -> RecordData {
+            let mut nested = RecordData::new();
+            for (mut path, val) in fields {
+                let name = match path.pop_front() {
+                    Some(name) => name,
+                    None => continue,
+                };
+                let val = if path.is_empty() {
+                    val
+                } else {
+                    Box::new(Term::Record(nest_fields(ast::deq((path, val)))).into())
+                };
+                match nested.iter_mut().find(|(p, _)| p.front() == Some(&name)) {
+                    Some((_, existing)) => {
+                        let left = std::mem::take(existing.as_mut());
+                        let combined = Term1::Operation(Box::new(left.into()), "∧", Box::new((*val).into()));
+                        **existing = combined.into();
+                    }
+                    None => nested.push_back((ast::path([name]), val)),
+                }
+            }
+            nested
+        }
+
+        /// Desugar a multi-line literal: drop the newline after the opening
+        /// `''` and the indentation shared by its lines. Empty lines do not
+        /// count, except for the last one (the indentation of the closing
+        /// `''`).
+        fn dedent(entries: Deq<TextEntry>) -> Deq<TextEntry> {
+            let mut chunks: Vec<String> = entries.iter().map(|(c, _)| c.replace("\r\n", "\n")).collect();
+            if let Some(first) = chunks.first_mut() {
+                if first.starts_with('\n') {
+                    first.remove(0);
+                }
+            }
+
+            // The leading text of each line (up to the first hole), and
+            // whether the line is empty. A chunk after a hole continues the
+            // line of the hole.
+            let mut lines = Vec::new();
+            for (n, (chunk, (_, hole))) in chunks.iter().zip(&entries).enumerate() {
+                let parts: Vec<&str> = chunk.split('\n').collect();
+                let last = parts.len() - 1;
+                for (i, part) in parts.into_iter().enumerate() {
+                    if i == 0 && n > 0 {
+                        continue;
+                    }
+                    lines.push((part, part.is_empty() && (i < last || hole.is_none())));
+                }
+            }
+            let count = lines.len();
+            let indent = lines
+                .into_iter()
+                .enumerate()
+                .filter(|(i, (_, empty))| !empty || i + 1 == count)
+                .map(|(_, (line, _))| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()])
+                .reduce(|a, b| {
+                    let shared = a.bytes().zip(b.bytes()).take_while(|(a, b)| a == b).count();
+                    &a[..shared]
+                })
+                .unwrap_or("");
+
+            let chunks: Vec<String> = chunks
+                .iter()
+                .enumerate()
+                .map(|(n, chunk)| {
+                    let lines = chunk.split('\n').enumerate().map(|(i, line)| match i {
+                        0 if n > 0 => line,
+                        _ => line.strip_prefix(indent).unwrap_or(line),
+                    });
+                    lines.collect::<Vec<_>>().join("\n")
+                })
+                .collect();
+            chunks
+                .into_iter()
+                .zip(entries)
+                .map(|(chunk, (_, hole))| (Cow::Owned(chunk), hole))
+                .collect()
+        }
+
+        /// Decode the escapes of a raw chunk of a `"` (1) or `''` (2) text
+        /// literal.
+        fn unescape(style: u8, raw: Cow<str>) -> Result<Cow<str>, String> {
+            Ok(match style {
+                1 if raw.contains('\\') => Cow::Owned(unescape_dquote(&raw)?),
+                2 if raw.contains("''") => Cow::Owned(unescape_ddquote(&raw)),
+                _ => raw,
+            })
+        }
+
+        fn unescape_dquote(raw: &str) -> Result<String, String> {
+            let mut value = String::with_capacity(raw.len());
+            let mut rest = raw;
+            while let Some(n) = rest.find('\\') {
+                value.push_str(&rest[..n]);
+                rest = &rest[n..];
+                match escaped(rest) {
+                    Some((c, len)) => {
+                        value.push(c);
+                        rest = &rest[len..];
+                    }
+                    None => {
+                        let end = rest[1..].find(|c: char| c.is_whitespace() || c == '\\').map_or(rest.len(), |n| n + 1);
+                        let sequence: String = rest[..end].chars().take(12).collect();
+                        return Err(format!("invalid escape sequence `{}`", sequence));
+                    }
+                }
+            }
+            value.push_str(rest);
+            Ok(value)
+        }
+
+        fn unescape_ddquote(raw: &str) -> String {
+            let mut value = String::with_capacity(raw.len());
+            let mut rest = raw;
+            while let Some(n) = rest.find("''") {
+                value.push_str(&rest[..n]);
+                rest = &rest[n + 2..];
+                // `'''` stands for `''` and `''${` for `${`.
+                if let Some(after) = rest.strip_prefix('\'') {
+                    value.push_str("''");
+                    rest = after;
+                } else if let Some(after) = rest.strip_prefix("${") {
+                    value.push_str("${");
+                    rest = after;
+                } else {
+                    value.push_str("''");
+                }
+            }
+            value.push_str(rest);
+            value
+        }
+
+        /// The character of the escape sequence at the start of `raw`, and
+        /// its length.
+        fn escaped(raw: &str) -> Option<(char, usize)> {
+            let c = match raw[1..].chars().next()? {
+                c @ ('"' | '\\' | '/' | '$') => c,
+                'b' => '\u{8}',
+                'f' => '\u{c}',
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                'u' => return unicode(raw),
+                _ => return None,
+            };
+            Some((c, 2))
+        }
+
+        /// `\uXXXX`, `\u{X…}` or a `\uXXXX\uXXXX` surrogate pair.
+        fn unicode(raw: &str) -> Option<(char, usize)> {
+            let code = |raw: &str| -> Option<(u32, usize)> {
+                let rest = raw.strip_prefix("\\u")?;
+                let (hex, len) = match rest.strip_prefix('{') {
+                    Some(braced) => {
+                        let end = braced.find('}')?;
+                        (&braced[..end], end + 4)
+                    }
+                    None => (rest.get(..4)?, 6),
+                };
+                Some((u32::from_str_radix(hex, 16).ok()?, len))
+            };
+            let (high, len) = code(raw)?;
+            if let 0xD800..=0xDBFF = high {
+                let (low, len2) = code(&raw[len..])?;
+                let c = 0x10000 + ((high - 0xD800) << 10) + low.checked_sub(0xDC00)?;
+                return Some((char::from_u32(c)?, len + len2));
+            }
+            Some((char::from_u32(high)?, len))
+        }
 
         // fn select2<T: Analogous<(Term<'i>, Deq<Term1<'i>>)>>(t: T) -> Term<'i> {
         // }
     }
+
+    pub mod import {
+        use super::*;
+
+        /// An import, or the reason its hash is invalid.
+        pub fn import<'s>(
+            (target, hash, mode): (ImportTarget<'s>, Option<&'s str>, Option<ImportMode>),
+        ) -> Result<Import<'s>, String> {
+            let t = Import {
+                target,
+                hash: hash.map(digest).transpose()?,
+                mode: mode.unwrap_or_default(),
+            };
+            log::trace!("Reduce {:?}", t);
+            Ok(t)
+        }
+
+        impl_report! {
+        local -> ImportTarget 's
+            : &'s str
+            = |s: &'s str| {
+                const PREFIXES: &[(&str, FilePrefix)] = &[
+                    ("../", FilePrefix::Parent),
+                    ("./", FilePrefix::Here),
+                    ("~/", FilePrefix::Home),
+                    ("/", FilePrefix::Absolute),
+                ];
+                let (prefix, path) = PREFIXES
+                    .iter()
+                    .find_map(|&(p, prefix)| s.strip_prefix(p).map(|path| (prefix, path)))
+                    .unwrap_or((FilePrefix::Here, s));
+                ImportTarget::Local(prefix, path.split('/').collect())
+            } }
+
+        impl_report! {
+        remote -> ImportTarget 's
+            : &'s str
+            = |s: &'s str| {
+                let (scheme, rest) = s.split_once("://").unwrap_or(("https", s));
+                let (rest, query) = match rest.split_once('?') {
+                    Some((rest, query)) => (rest, Some(query)),
+                    None => (rest, None),
+                };
+                let (authority, path) = match rest.split_once('/') {
+                    Some((authority, path)) => (authority, path.split('/').collect()),
+                    None => (rest, Deq::new()),
+                };
+                ImportTarget::Remote(Url { scheme, authority, path, query })
+            } }
+
+        impl_report! {
+        env -> ImportTarget 's
+            : &'s str
+            = |s: &'s str| {
+                let name = s.strip_prefix("env:").unwrap_or(s);
+                match name.strip_prefix('"').and_then(|n| n.strip_suffix('"')) {
+                    Some(quoted) => ImportTarget::Env(Cow::Owned(unescape_env(quoted))),
+                    None => ImportTarget::Env(Cow::Borrowed(name)),
+                }
+            } }
+
+        pub fn mode(name: &str) -> Option<ImportMode> {
+            Some(match name {
+                "Text" => ImportMode::Text,
+                "Location" => ImportMode::Location,
+                "Bytes" => ImportMode::Bytes,
+                _ => return None,
+            })
+        }
+
+        /// The bytes of a `sha256:` hash, unless it is not 64 hex digits.
+        fn digest(hash: &str) -> Result<[u8; 32], String> {
+            let hex = hash.strip_prefix("sha256:").unwrap_or(hash);
+            if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(format!("invalid sha256 hash: {}", hash));
+            }
+            let mut digest = [0; 32];
+            for (i, byte) in digest.iter_mut().enumerate() {
+                *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+            }
+            Ok(digest)
+        }
+
+        fn unescape_env(quoted: &str) -> String {
+            let mut name = String::new();
+            let mut chars = quoted.chars();
+            while let Some(c) = chars.next() {
+                let c = match c {
+                    '\\' => match chars.next() {
+                        Some('a') => '\x07',
+                        Some('b') => '\x08',
+                        Some('f') => '\x0c',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('v') => '\x0b',
+                        Some(c) => c,
+                        None => break,
+                    },
+                    c => c,
+                };
+                name.push(c);
+            }
+            name
+        }
+    }
 }
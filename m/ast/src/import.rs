@@ -0,0 +1,62 @@
+use super::Deq;
+use std::borrow::Cow;
+
+/// An import: what to read, the digest it must have and how to interpret it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Import<'i> {
+    pub target: ImportTarget<'i>,
+    /// The sha256 digest of the normalized import.
+    pub hash: Option<[u8; 32]>,
+    pub mode: ImportMode,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImportTarget<'i> {
+    /// A file, by its path components after the prefix.
+    Local(FilePrefix, Deq<&'i str>),
+    Remote(Url<'i>),
+    /// An environment variable, by its (unescaped) name.
+    Env(Cow<'i, str>),
+    Missing,
+}
+
+/// Where a local path starts from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FilePrefix {
+    /// `./`
+    Here,
+    /// `../`
+    Parent,
+    /// `~/`
+    Home,
+    /// `/`
+    Absolute,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Url<'i> {
+    pub scheme: &'i str,
+    pub authority: &'i str,
+    pub path: Deq<&'i str>,
+    pub query: Option<&'i str>,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ImportMode {
+    #[default]
+    Code,
+    Text,
+    Location,
+    Bytes,
+}
+
+impl FilePrefix {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Here => "./",
+            Self::Parent => "../",
+            Self::Home => "~/",
+            Self::Absolute => "/",
+        }
+    }
+}
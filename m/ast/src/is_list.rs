@@ -37,10 +37,7 @@ impl<'i> IsList<'i> for Term1<'i> {
 
 impl<'i> IsList<'i> for Term<'i> {
     fn is_list(&self) -> bool {
-        match self {
-            Term::List(_) => true,
-            _ => false,
-        }
+        matches!(self, Term::List(_))
     }
     fn get_list_mut(&mut self) -> &mut Deq<Val<'i>> {
         match self {
@@ -50,12 +47,12 @@ impl<'i> IsList<'i> for Term<'i> {
     }
 }
 
-impl<'a, 'i, T> IsList<'i> for &'a mut T
+impl<'i, T> IsList<'i> for &mut T
 where
     T: IsList<'i>,
 {
     fn is_list(&self) -> bool {
-        T::is_list(&*self)
+        T::is_list(*self)
     }
     fn get_list_mut(&mut self) -> &mut Deq<Val<'i>> {
         T::get_list_mut(*self)
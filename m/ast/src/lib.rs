@@ -1,8 +1,14 @@
 pub const VERSION: &str = "0.0.1";
 
+mod import;
 mod is_list;
+mod span;
+pub use import::{FilePrefix, Import, ImportMode, ImportTarget, Url};
 pub use is_list::IsList;
+pub use span::{node_id, Location, NodeId, Span, Spans};
+pub use num_bigint::{BigInt, BigUint};
 pub use std::collections::VecDeque as Deq;
+use std::borrow::Cow;
 
 pub type Ident<'i> = &'i str;
 pub type Path<'i> = Deq<Ident<'i>>;
@@ -14,7 +20,7 @@ pub type Val<'i> = Box<Expr<'i>>;
 
 pub type LetStmt<'i> = (Ident<'i>, Option<Val<'i>>, Val<'i>);
 
-pub type TextEntry<'i> = (&'i str, Option<Val<'i>>);
+pub type TextEntry<'i> = (Cow<'i, str>, Option<Val<'i>>);
 
 pub type RecordEntry<'i> = (Path<'i>, Val<'i>);
 pub type RecordData<'i> = Deq<RecordEntry<'i>>;
@@ -37,14 +43,19 @@ pub enum Term1<'i> {
     With(Box<Term1<'i>>, Path<'i>, Box<Term1<'i>>),
     Operation(Box<Term1<'i>>, &'i str, Box<Term1<'i>>),
     IfThenElse(Val<'i>, Val<'i>, Val<'i>),
+    Assert(Val<'i>),
     Ascribe(Box<Term1<'i>>, Val<'i>),
     Construct(Box<Term1<'i>>, RecordData<'i>),
+    /// `a ? b`: `b` if `a` fails to resolve.
+    ImportAlt(Box<Term1<'i>>, Box<Term1<'i>>),
 }
 
 #[derive(Clone, Debug)]
 pub enum Term<'i> {
-    Integer(i32),
-    Double(f32),
+    Natural(BigUint),
+    Integer(BigInt),
+    Double(f64),
+    Bytes(Vec<u8>),
     FieldAccess(Box<Term<'i>>, Ident<'i>),
     Project(u8, Box<Term<'i>>, Deq<Term1<'i>>),
     Path(TermPath<'i>),
@@ -54,14 +65,11 @@ pub enum Term<'i> {
     Record(RecordData<'i>),
     TypeRecord(RecordData<'i>),
     TypeEnum(TypeEnumData<'i>),
-    Import {
-        path: &'i str,
-        as_: Option<&'i str>,
-        guard: Option<&'i str>,
-        fall: Option<(&'i str, Option<&'i str>)>,
-    },
+    Import(Box<Import<'i>>),
     Expr(Val<'i>),
     Merge(RecordData<'i>, Box<Term<'i>>),
+    ToMap(Box<Term<'i>>),
+    ShowConstructor(Box<Term<'i>>),
     Embed(String),
 }
 
@@ -71,9 +79,13 @@ pub enum Token<'i> {
     Natural(&'i str),
     Negative(&'i str),
     Double(&'i str),
+    Bytes(&'i str),
     Text(&'i str),
     RelUri(&'i str),
+    AbsUri(&'i str),
+    HomeUri(&'i str),
     HttpUri(&'i str),
+    EnvUri(&'i str),
     Missing(&'i str),
     Sha256(&'i str),
     Conj1(&'i str),
@@ -118,6 +130,9 @@ pub enum Token<'i> {
     Whitespace(&'i str),
     RawText(&'i str),
     Merge(&'i str),
+    Assert(&'i str),
+    ToMap(&'i str),
+    ShowConstructor(&'i str),
     Equiv(&'i str),
     LogicConj(&'i str),
     LogicDisj(&'i str),
@@ -132,10 +147,12 @@ impl<'s> AsRef<str> for Token<'s> {
         use Token::*;
         match self {
             As(s) | Missing(s) | LogicNeq(s) | LogicEq(s) | Natural(s) | Scope(s)
-            | LogicConj(s) | LogicDisj(s) | Equiv(s) | Double(s) | Merge(s) | DDQuote(s)
-            | DColon(s) | RawText(s) | Ident(s) | Negative(s) | Text(s) | RelUri(s)
-            | HttpUri(s) | Sha256(s) | Conj1(s) | Conj2(s) | Alt(s) | Lambda(s) | Arrow(s)
-            | Equals(s) | Let(s) | In(s) | LPar(s) | RPar(s) | Colon(s) | Forall(s)
+            | LogicConj(s) | LogicDisj(s) | Equiv(s) | Double(s) | Bytes(s) | Merge(s) | Assert(s) | ToMap(s)
+            | ShowConstructor(s)
+            | DDQuote(s) | DColon(s) | RawText(s) | Ident(s) | Negative(s) | Text(s)
+            | RelUri(s) | AbsUri(s) | HomeUri(s) | HttpUri(s) | EnvUri(s) | Sha256(s) | Conj1(s)
+            | Conj2(s) | Alt(s) | Lambda(s)
+            | Arrow(s) | Equals(s) | Let(s) | In(s) | LPar(s) | RPar(s) | Colon(s) | Forall(s)
             | TextConcat(s) | ListConcat(s) | Plus(s) | Div(s) | Star(s) | Minus(s) | LBrace(s)
             | RBrace(s) | LBracket(s) | RBracket(s) | LAngle(s) | RAngle(s) | Comma(s) | Dot(s)
             | Pipe(s) | DQuote(s) | SQuote(s) | Questionmark(s) | If(s) | Then(s) | Else(s)
@@ -149,29 +166,29 @@ impl<'s> Token<'s> {
         self.as_ref()
     }
 
-    pub fn is_ident<I: ?Sized>(&self, name: &I) -> bool
+    pub fn is_ident<I>(&self, name: &I) -> bool
     where
-        I: std::cmp::PartialEq<str>,
+        I: std::cmp::PartialEq<str> + ?Sized,
     {
-        match self {
-            &Token::Ident(v) if name == v => true,
-            _ => false,
-        }
+        matches!(self, &Token::Ident(v) if name == v)
     }
 
     pub fn is_keyword(&self) -> bool {
-        match self {
+        matches!(
+            self,
             Token::Let(_)
-            | Token::In(_)
-            | Token::With(_)
-            | Token::If(_)
-            | Token::Then(_)
-            | Token::Else(_)
-            | Token::Merge(_)
-            | Token::Missing(_)
-            | Token::As(_) => true,
-            _ => false,
-        }
+                | Token::In(_)
+                | Token::With(_)
+                | Token::If(_)
+                | Token::Then(_)
+                | Token::Else(_)
+                | Token::Merge(_)
+                | Token::Assert(_)
+                | Token::ToMap(_)
+                | Token::ShowConstructor(_)
+                | Token::Missing(_)
+                | Token::As(_)
+        )
     }
 
     pub fn set_val<'u>(&self, val: &'u str) -> Token<'u> {
@@ -181,9 +198,13 @@ impl<'s> Token<'s> {
             Natural(_) => Natural(val),
             Negative(_) => Negative(val),
             Double(_) => Double(val),
+            Bytes(_) => Bytes(val),
             Text(_) => Text(val),
             RelUri(_) => RelUri(val),
+            AbsUri(_) => AbsUri(val),
+            HomeUri(_) => HomeUri(val),
             HttpUri(_) => HttpUri(val),
+            EnvUri(_) => EnvUri(val),
             Missing(_) => Missing(val),
             Sha256(_) => Sha256(val),
             Conj1(_) => Conj1(val),
@@ -228,6 +249,9 @@ impl<'s> Token<'s> {
             Whitespace(_) => Whitespace(val),
             RawText(_) => RawText(val),
             Merge(_) => Merge(val),
+            Assert(_) => Assert(val),
+            ToMap(_) => ToMap(val),
+            ShowConstructor(_) => ShowConstructor(val),
             Equiv(_) => Equiv(val),
             LogicConj(_) => LogicConj(val),
             LogicDisj(_) => LogicDisj(val),
@@ -253,13 +277,32 @@ where
     p.into_iter().map(|s| s.to_owned()).collect()
 }
 
+/// The precedence of a binary operator; higher binds tighter.
+pub fn precedence(op: &str) -> u8 {
+    match op {
+        "?" => 1,
+        "||" => 2,
+        "+" => 3,
+        "++" => 4,
+        "#" => 5,
+        "&&" => 6,
+        "∧" | "/\\" => 7,
+        "⫽" | "//" => 8,
+        "⩓" | "//\\\\" => 9,
+        "*" => 10,
+        "==" => 11,
+        "!=" => 12,
+        _ => 0, // ≡
+    }
+}
+
 pub fn utf8len(c: char) -> usize {
     let mut buf = [0u8; 4];
-    c.encode_utf8(&mut buf).as_bytes().len()
+    c.encode_utf8(&mut buf).len()
 }
 
 pub fn const_0_term<'i>() -> Term<'i> {
-    Term::Integer(0)
+    Term::Natural(BigUint::default())
 }
 pub fn const_0_term1<'i>() -> Term1<'i> {
     Term1::Term(const_0_term())
@@ -269,7 +312,7 @@ pub fn const_0_expr<'i>() -> Expr<'i> {
     Expr::Term1(const_0_term1())
 }
 
-pub fn var_expr(s: &str) -> Expr {
+pub fn var_expr(s: &str) -> Expr<'_> {
     let s = match s {
         "Type" => "`Type`",
         "Kind" => "`Kind`",
@@ -295,9 +338,9 @@ impl<'i> Default for Expr<'i> {
     }
 }
 
-impl<'i> Into<Expr<'i>> for Term<'i> {
-    fn into(self) -> Expr<'i> {
-        <Term1<'i> as From<Term<'i>>>::from(self).into()
+impl<'i> From<Term<'i>> for Expr<'i> {
+    fn from(val: Term<'i>) -> Self {
+        <Term1<'i> as From<Term<'i>>>::from(val).into()
     }
 }
 
@@ -312,3 +355,21 @@ impl<'i> From<Term<'i>> for Term1<'i> {
         Term1::Term(t)
     }
 }
+
+impl<'i> From<Expr<'i>> for Term1<'i> {
+    fn from(e: Expr<'i>) -> Term1<'i> {
+        match e {
+            Expr::Term1(t1) => t1,
+            e => Term1::Term(Term::Expr(Box::new(e))),
+        }
+    }
+}
+
+impl<'i> From<Expr<'i>> for Term<'i> {
+    fn from(e: Expr<'i>) -> Term<'i> {
+        match e {
+            Expr::Term1(Term1::Term(t)) => t,
+            e => Term::Expr(Box::new(e)),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::{collections::HashMap as Map, fmt};
+
+/// A byte range into a source text.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The identity of a boxed node: its address.
+///
+/// Ids are only meaningful while the tree they were taken from is alive and
+/// unmodified (eval replaces the trees it normalizes).
+pub type NodeId = usize;
+
+pub fn node_id<T>(node: &T) -> NodeId {
+    node as *const T as usize
+}
+
+/// A resolved position in a source file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.col)
+    }
+}
+
+/// The side table of spans of a parsed tree, keyed by node id.
+///
+/// Every boxed node (`Val`, `Box<Term1>`, `Box<Term>`) is recorded by the
+/// parser. Unboxed nodes are covered by their closest boxed ancestor, and
+/// borrowed slices (identifiers, import paths, text) can be located directly.
+#[derive(Debug, Default)]
+pub struct Spans<'i> {
+    pub file: &'i str,
+    pub text: &'i str,
+    nodes: Map<NodeId, Span>,
+}
+
+impl<'i> Spans<'i> {
+    pub fn new(file: &'i str, text: &'i str) -> Self {
+        Self {
+            file,
+            text,
+            nodes: <_>::default(),
+        }
+    }
+
+    /// Box `node` and record its span.
+    pub fn boxed<T>(&mut self, start: usize, node: T, end: usize) -> Box<T> {
+        let node = Box::new(node);
+        self.nodes.insert(node_id(node.as_ref()), Span { start, end });
+        node
+    }
+
+    pub fn get<T>(&self, node: &T) -> Option<Span> {
+        self.of_id(node_id(node))
+    }
+
+    /// The span of the node `id` was taken from.
+    pub fn of_id(&self, id: NodeId) -> Option<Span> {
+        self.nodes.get(&id).copied()
+    }
+
+    /// The span of a slice borrowed from the source text.
+    pub fn of_str(&self, s: &str) -> Option<Span> {
+        let base = self.text.as_ptr() as usize;
+        let start = (s.as_ptr() as usize).checked_sub(base)?;
+        let end = start + s.len();
+        if end <= self.text.len() {
+            Some(Span { start, end })
+        } else {
+            None
+        }
+    }
+
+    /// The 1-based line and column (in chars) of a byte offset.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.text.len());
+        let before = &self.text[..offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|n| n + 1).unwrap_or(0);
+        let col = before[line_start..].chars().count() + 1;
+        (line, col)
+    }
+
+    /// The byte offset of a 1-based line and column (in chars), as
+    /// `line_col` gives them.
+    pub fn offset(&self, line: usize, col: usize) -> usize {
+        let line_start = match line {
+            0 | 1 => 0,
+            n => self.text.match_indices('\n').nth(n - 2).map(|(i, _)| i + 1).unwrap_or(self.text.len()),
+        };
+        let rest = &self.text[line_start..];
+        line_start + rest.char_indices().nth(col.saturating_sub(1)).map(|(i, _)| i).unwrap_or(rest.len())
+    }
+
+    pub fn locate(&self, span: Span) -> Location {
+        let (line, col) = self.line_col(span.start);
+        Location {
+            file: self.file.to_owned(),
+            line,
+            col,
+        }
+    }
+}
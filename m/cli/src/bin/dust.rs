@@ -1,8 +1,15 @@
-use error::Result;
+use error::{Error, Result};
 
-fn main() -> Result<()> {
+fn main() {
     pretty_env_logger::init();
 
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
     let mut home = std::env::var("HOME")?;
     if !home.ends_with("/") {
         home.push('/');
@@ -15,8 +22,9 @@ fn main() -> Result<()> {
     let mut opt_list_files = false;
     let mut opt_show_ast = false;
     let mut opt_help = false;
-    let mut opt_input_file_path = format!("/dev/stdin");
+    let mut opt_input_file_path = "/dev/stdin".to_string();
     let mut opt_eval = false;
+    let mut opt_typecheck = None;
     for arg in std::env::args().skip(1) {
         match arg.as_str() {
             "--show" => opt_show = true,
@@ -35,6 +43,8 @@ fn main() -> Result<()> {
             "--no_fetch" => r.enable_fetch = false,
             "--help" => opt_help = true,
             "--eval" => opt_eval = true,
+            "--typecheck" => opt_typecheck = Some(true),
+            "--no_typecheck" => opt_typecheck = Some(false),
             _ => opt_input_file_path = arg,
         }
     }
@@ -45,6 +55,7 @@ fn main() -> Result<()> {
             "  --[no_]resolve     true                    Resolve imports",
             "  --[no_]fetch       false                   Download http imports to ~/.cache/dust (requires resolve)",
             "  --[no_]show        !(resolve || fetch)     Show dhall.",
+            "  --[no_]typecheck   eval                    Typecheck the resolved dhall (requires resolve)",
             "  --help             false                   Show help and exit.",
             "  --ast!             false                   Show the dhall AST. Turns show off. [!] Care!",
             "  --files            false                   Show the loaded files. Turns show off.",
@@ -70,12 +81,23 @@ fn main() -> Result<()> {
         &opt_input_file_path
     );
 
+    if opt_typecheck.unwrap_or(opt_eval) && r.enable_resolve {
+        let mut spans = parse::Spans::new("(resolved)", &resolved_code);
+        let ast = parse::parse_spanned(&mut spans, &resolved_code)
+            .map_err(|e| Error::parse(&spans, e))?;
+        let typ = eval::TypeCheck::type_of(&ast, &mut eval::TypeCtx::new().with_spans(&spans))
+            .map_err(|e| r.relocate(&resolved_code, e))?;
+        log::info!("type: {}", show::Show(&typ));
+    }
+
     if opt_show || opt_show_ast {
         let source = if r.enable_resolve {
             if opt_eval {
-                let mut ctx = eval::ctx();
-                let mut ast = parse::parse_str(&resolved_code)?;
-                let _ctx = eval::eval(&mut ctx, &mut ast)?;
+                let mut spans = parse::Spans::new("(resolved)", &resolved_code);
+                let mut ast = parse::parse_spanned(&mut spans, &resolved_code)
+                    .map_err(|e| Error::parse(&spans, e))?;
+                let mut ctx = eval::ctx().with_spans(&spans);
+                let _ctx = eval::eval(&mut ctx, &mut ast).map_err(|e| r.relocate(&resolved_code, e))?;
                 format!("{}", show::Show(&ast))
             } else {
                 resolved_code
@@ -4,7 +4,7 @@ fn main() {
     let mut buf = String::new();
     let mut inp = std::fs::File::open("sample.dhall").unwrap();
     std::io::Read::read_to_string(&mut inp, &mut buf).unwrap();
-    let mut lex = lex::Lex::new(&buf);
-    let r = parse::dhall::ExprParser::new().parse(&mut lex).unwrap();
+    let mut spans = parse::Spans::new("sample.dhall", &buf);
+    let r = parse::parse_spanned(&mut spans, &buf).unwrap();
     eprintln!("{:?}", r);
 }
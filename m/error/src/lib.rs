@@ -1,6 +1,6 @@
 pub const VERSION: &str = "0.0.1";
 
-use std::{env, io, result};
+use std::{env, fmt, io, result};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Source {
@@ -8,15 +8,96 @@ pub enum Source {
     Io(#[from] io::Error),
     #[error("env var: {:?}", .0)]
     Var(#[from] env::VarError),
-    #[error("parse: {:?}", .0)]
-    Lalrpop(parse::ParseError<'static>),
+    #[error("parse: {}", .0)]
+    Parse(Box<parse::Diagnostic>),
+    #[error("type: {}", .0)]
+    Type(#[from] TypeError),
+    #[error("eval: {}", .0)]
+    Eval(#[from] EvalError),
+    #[error("import: {}", .0)]
+    Import(String),
     #[error("{}", .0)]
     Any(String),
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum TypeError {
+    #[error("unbound variable: {}", .0)]
+    UnboundVariable(String),
+    #[error("{} has no type", .0)]
+    Untyped(String),
+    #[error("not a type: {} : {}", .0, .1)]
+    NotAType(String, String),
+    #[error("lambda without annotation: {}", .0)]
+    UnannotatedLambda(String),
+    #[error("not a function: {} : {}", .0, .1)]
+    NotAFunction(String, String),
+    #[error("type mismatch: expected {}, found {}", .0, .1)]
+    Mismatch(String, String),
+    #[error("invalid operands for {}: {} and {}", .0, .1, .2)]
+    InvalidOperands(String, String, String),
+    #[error("if predicate is not a Bool: {}", .0)]
+    InvalidPredicate(String),
+    #[error("empty list without a List annotation: {}", .0)]
+    InvalidListType(String),
+    #[error("list elements must be terms: {}", .0)]
+    InvalidListElement(String),
+    #[error("not a record: {} : {}", .0, .1)]
+    NotARecord(String, String),
+    #[error("not a record type: {}", .0)]
+    NotARecordType(String),
+    #[error("not a union type: {}", .0)]
+    NotAUnion(String),
+    #[error("missing field: .{} in {}", .0, .1)]
+    MissingField(String, String),
+    #[error("duplicate field: {}", .0)]
+    DuplicateField(String),
+    #[error("field collision: {}", .0)]
+    FieldCollision(String),
+    #[error("invalid merge: {}", .0)]
+    InvalidMerge(String),
+    #[error("invalid toMap: {}", .0)]
+    InvalidToMap(String),
+    #[error("not an equivalence: {}", .0)]
+    NotAnEquivalence(String),
+    #[error("assertion failed: {} is not equivalent to {}", .0, .1)]
+    AssertionFailed(String, String),
+    #[error("unsupported: {}", .0)]
+    Unsupported(String),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum EvalError {
+    #[error("unbound variable: {}@{}", .0, .1)]
+    UnboundVariable(String, u16),
+    #[error("not a function: {}", .0)]
+    NotAFunction(String),
+    #[error("invalid operation: {} {} {}", .1, .0, .2)]
+    InvalidOperation(String, String, String),
+    #[error("if predicate is not a Bool: {}", .0)]
+    InvalidPredicate(String),
+    #[error("not a record: {}", .0)]
+    NotARecord(String),
+    #[error("missing field: .{} in {}", .0, .1)]
+    MissingField(String, String),
+    #[error("field collision: {}", .0)]
+    FieldCollision(String),
+    #[error("projection selector is not a label: {}", .0)]
+    InvalidSelector(String),
+    #[error("merge argument is not a union constructor: {}", .0)]
+    InvalidMergeArgument(String),
+    #[error("not a union value: {}", .0)]
+    NotAUnion(String),
+    #[error("assertion failed: {} is not equivalent to {}", .0, .1)]
+    AssertionFailed(String, String),
+    #[error("cannot evaluate: {}", .0)]
+    Unsupported(String),
+}
+
 #[derive(Debug)]
 pub struct Error {
     pub source: Source,
+    pub location: Option<ast::Location>,
 }
 
 pub type Result<T> = result::Result<T, Error>;
@@ -30,12 +111,47 @@ macro_rules! bail {
 
 impl Error {
     pub fn new(source: Source) -> Self {
-        Self { source }
+        Self {
+            source,
+            location: None,
+        }
+    }
+
+    /// A parse error, pointing into the source of `spans`.
+    pub fn parse(spans: &parse::Spans, e: parse::ParseError) -> Self {
+        Self::new(Source::Parse(Box::new(parse::Diagnostic::locate(spans, &e))))
+    }
+
+    /// Locate the error at `location`, unless it is already located more precisely.
+    pub fn at(mut self, location: Option<ast::Location>) -> Self {
+        if self.location.is_none() {
+            self.location = location;
+        }
+        self
     }
 
     pub fn any<M: Into<String>>(msg: M) -> Self {
         Self::new(Source::Any(msg.into()))
     }
+
+    pub fn import<M: Into<String>>(msg: M) -> Self {
+        Self::new(Source::Import(msg.into()))
+    }
+
+    /// Whether an import failed to resolve, as opposed to resolving to
+    /// invalid code. Only the former falls back to the alternative of `?`.
+    pub fn is_import_failure(&self) -> bool {
+        matches!(self.source, Source::Io(_) | Source::Var(_) | Source::Import(_))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{}: {}", location, self.source),
+            None => write!(f, "{}", self.source),
+        }
+    }
 }
 
 impl<T> From<T> for Error
@@ -47,10 +163,9 @@ where
     }
 }
 
-impl<'i, E> From<parse::ParseErrorE<'i, E>> for Error {
-    fn from(e: parse::ParseErrorE<'i, E>) -> Self {
-        let e = e.map_token(|t| t.set_val("")).map_error(|_| parse::Error);
-        Self::new(Source::Lalrpop(e))
+impl<'i> From<parse::ParseError<'i>> for Error {
+    fn from(e: parse::ParseError<'i>) -> Self {
+        Self::new(Source::Parse(Box::new(parse::Diagnostic::new(&e))))
     }
 }
 
@@ -1,133 +1,189 @@
-pub trait ASubstitution {
-    fn commit_or_free_name(&mut self, name: &str, commit: bool);
+use ast::{Expr, Ident, Term, Term1};
 
-    fn commit_name(&mut self, name: &str) {
-        self.commit_or_free_name(name, true);
-    }
-
-    fn free_name(&mut self, name: &str) {
-        self.commit_or_free_name(name, false);
-    }
+pub trait VisitVars<'i> {
+    /// Visit every variable, along with the binders it is under.
+    fn visit_vars<F>(&mut self, bound: &mut Vec<Ident<'i>>, f: &mut F)
+    where
+        F: FnMut(&mut Term<'i>, &[Ident<'i>]);
 }
 
-impl<'i> ASubstitution for ast::Expr<'i> {
-    fn commit_or_free_name(&mut self, name: &str, commit: bool) {
+impl<'i> VisitVars<'i> for Expr<'i> {
+    fn visit_vars<F>(&mut self, bound: &mut Vec<Ident<'i>>, f: &mut F)
+    where
+        F: FnMut(&mut Term<'i>, &[Ident<'i>]),
+    {
         use ast::Expr::*;
         match self {
-            Term1(t1) => t1.commit_or_free_name(name, commit),
+            Term1(t1) => t1.visit_vars(bound, f),
             Let(defs, val) => {
-                for (_, a, b) in defs {
-                    a.commit_or_free_name(name, commit);
-                    b.commit_or_free_name(name, commit);
+                let depth = bound.len();
+                for (name, typ, val) in defs {
+                    typ.visit_vars(bound, f);
+                    val.visit_vars(bound, f);
+                    bound.push(name);
                 }
-                val.commit_or_free_name(name, commit);
+                val.visit_vars(bound, f);
+                bound.truncate(depth);
             }
-            Lambda(_, a, b) => {
-                a.commit_or_free_name(name, commit);
-                b.commit_or_free_name(name, commit);
+            Lambda(name, typ, val) => {
+                typ.visit_vars(bound, f);
+                bound.push(name);
+                val.visit_vars(bound, f);
+                bound.pop();
             }
         }
     }
 }
 
-impl<'i> ASubstitution for ast::Term1<'i> {
-    fn commit_or_free_name(&mut self, name: &str, commit: bool) {
+impl<'i> VisitVars<'i> for Term1<'i> {
+    fn visit_vars<F>(&mut self, bound: &mut Vec<Ident<'i>>, f: &mut F)
+    where
+        F: FnMut(&mut Term<'i>, &[Ident<'i>]),
+    {
         use ast::Term1::*;
         match self {
-            Term(t) => {
-                t.commit_or_free_name(name, commit);
-            }
+            Term(t) => t.visit_vars(bound, f),
             Evaluation(a, b) => {
-                a.commit_or_free_name(name, commit);
-                b.commit_or_free_name(name, commit);
-            }
-            Arrow(_, a, b) => {
-                a.commit_or_free_name(name, commit);
-                b.commit_or_free_name(name, commit);
+                a.visit_vars(bound, f);
+                b.visit_vars(bound, f);
             }
-            With(a, _, b) => {
-                a.commit_or_free_name(name, commit);
-                b.commit_or_free_name(name, commit);
+            Arrow(name, a, b) => {
+                a.visit_vars(bound, f);
+                bound.push(name.unwrap_or("_"));
+                b.visit_vars(bound, f);
+                bound.pop();
             }
-            Operation(a, _, b) => {
-                a.commit_or_free_name(name, commit);
-                b.commit_or_free_name(name, commit);
+            With(a, _, b) | Operation(a, _, b) | ImportAlt(a, b) => {
+                a.visit_vars(bound, f);
+                b.visit_vars(bound, f);
             }
             IfThenElse(c, a, b) => {
-                c.commit_or_free_name(name, commit);
-                a.commit_or_free_name(name, commit);
-                b.commit_or_free_name(name, commit);
+                c.visit_vars(bound, f);
+                a.visit_vars(bound, f);
+                b.visit_vars(bound, f);
             }
+            Assert(typ) => typ.visit_vars(bound, f),
             Ascribe(a, b) => {
-                a.commit_or_free_name(name, commit);
-                b.commit_or_free_name(name, commit);
+                a.visit_vars(bound, f);
+                b.visit_vars(bound, f);
             }
-            Construct(a, b) => {
-                a.commit_or_free_name(name, commit);
-                for (_, b) in b {
-                    b.commit_or_free_name(name, commit);
+            Construct(a, fields) => {
+                a.visit_vars(bound, f);
+                for (_, val) in fields {
+                    val.visit_vars(bound, f);
                 }
             }
         }
     }
 }
 
-impl<'i> ASubstitution for ast::Term<'i> {
-    fn commit_or_free_name(&mut self, name: &str, commit: bool) {
+impl<'i> VisitVars<'i> for Term<'i> {
+    fn visit_vars<F>(&mut self, bound: &mut Vec<Ident<'i>>, f: &mut F)
+    where
+        F: FnMut(&mut Term<'i>, &[Ident<'i>]),
+    {
         use ast::Term::*;
         match self {
-            Var(n, s) if *n == name && commit => *s += 1,
-            Var(n, s) if *n == name && *s > 0 => *s -= 1,
-            FieldAccess(t, _) => t.commit_or_free_name(name, commit),
-            Project(_, t, _) => t.commit_or_free_name(name, commit),
-            Text(_, t) => {
-                for (_, t) in t {
-                    t.commit_or_free_name(name, commit);
+            Var(_, _) => f(self, bound),
+            FieldAccess(t, _) | Project(1, t, _) => t.visit_vars(bound, f),
+            Project(_, t, selector) => {
+                t.visit_vars(bound, f);
+                for s in selector {
+                    s.visit_vars(bound, f);
                 }
             }
-            List(t) => {
-                for t in t {
-                    t.commit_or_free_name(name, commit)
+            Path(ts) => {
+                for t in ts {
+                    t.visit_vars(bound, f);
                 }
             }
-            Record(t) => {
-                for (_, t) in t {
-                    t.commit_or_free_name(name, commit)
+            Text(_, entries) => {
+                for (_, val) in entries {
+                    val.visit_vars(bound, f);
                 }
             }
-            TypeRecord(t) => {
-                for (_, t) in t {
-                    t.commit_or_free_name(name, commit)
+            List(vals) => {
+                for val in vals {
+                    val.visit_vars(bound, f);
                 }
             }
-            TypeEnum(t) => {
-                for (_, t) in t {
-                    t.commit_or_free_name(name, commit)
+            Record(fields) | TypeRecord(fields) => {
+                for (_, val) in fields {
+                    val.visit_vars(bound, f);
                 }
             }
-            Expr(t) => t.commit_or_free_name(name, commit),
-            Merge(a, b) => {
-                for (_, a) in a {
-                    a.commit_or_free_name(name, commit);
+            TypeEnum(alts) => {
+                for (_, typ) in alts {
+                    typ.visit_vars(bound, f);
                 }
-                b.commit_or_free_name(name, commit);
             }
-            Integer(_) | Double(_) | Path(_) | Import { .. } | Embed(_) | Var(_, _) => (),
+            Expr(e) => e.visit_vars(bound, f),
+            Merge(handlers, t) => {
+                for (_, val) in handlers {
+                    val.visit_vars(bound, f);
+                }
+                t.visit_vars(bound, f);
+            }
+            ToMap(t) | ShowConstructor(t) => t.visit_vars(bound, f),
+            Natural(_) | Integer(_) | Double(_) | Bytes(_) | Import(_) | Embed(_) => (),
         }
     }
 }
 
-impl<T: ASubstitution> ASubstitution for Option<T> {
-    fn commit_or_free_name(&mut self, name: &str, commit: bool) {
-        match self {
-            Some(t) => t.commit_or_free_name(name, commit),
-            _ => (),
+impl<'i, T: VisitVars<'i>> VisitVars<'i> for Option<T> {
+    fn visit_vars<F>(&mut self, bound: &mut Vec<Ident<'i>>, f: &mut F)
+    where
+        F: FnMut(&mut Term<'i>, &[Ident<'i>]),
+    {
+        if let Some(t) = self {
+            t.visit_vars(bound, f);
         }
     }
 }
 
-impl<T: ASubstitution> ASubstitution for Box<T> {
-    fn commit_or_free_name(&mut self, name: &str, commit: bool) {
-        self.as_mut().commit_or_free_name(name, commit)
+impl<'i, T: VisitVars<'i>> VisitVars<'i> for Box<T> {
+    fn visit_vars<F>(&mut self, bound: &mut Vec<Ident<'i>>, f: &mut F)
+    where
+        F: FnMut(&mut Term<'i>, &[Ident<'i>]),
+    {
+        self.as_mut().visit_vars(bound, f)
     }
 }
+
+pub fn count(bound: &[Ident], name: &str) -> u16 {
+    bound.iter().filter(|&&b| b == name).count() as u16
+}
+
+/// ↑(d, name, min): shift the free occurrences of `name` by `d`.
+pub fn shift<'i, T: VisitVars<'i>>(t: &mut T, d: i32, name: &str, min: u16) {
+    t.visit_vars(&mut <_>::default(), &mut |t: &mut Term<'i>, bound: &[Ident<'i>]| {
+        if let Term::Var(n, idx) = t {
+            if *n == name && *idx >= min + count(bound, name) {
+                *idx = (*idx as i32 + d) as u16;
+            }
+        }
+    })
+}
+
+/// t[name@idx ≔ val]
+pub fn subst<'i, T: VisitVars<'i>>(t: &mut T, name: &str, idx: u16, val: &Expr<'i>) {
+    t.visit_vars(&mut <_>::default(), &mut |t: &mut Term<'i>, bound: &[Ident<'i>]| {
+        match t {
+            Term::Var(n, i) if *n == name && *i == idx + count(bound, name) => (),
+            _ => return,
+        }
+        let mut val = val.to_owned();
+        for b in bound {
+            shift(&mut val, 1, b, 0);
+        }
+        *t = val.into();
+    })
+}
+
+/// Substitute `val` for the variable bound by `name`, in `body`.
+pub fn beta<'i>(body: &mut Expr<'i>, name: &str, val: &Expr<'i>) {
+    let mut val = val.to_owned();
+    shift(&mut val, 1, name, 0);
+    subst(body, name, 0, &val);
+    shift(body, -1, name, 0);
+}
@@ -0,0 +1,255 @@
+use super::{
+    eval::{apply, conv, operation, text, unquote, Closure, Context, Value, V},
+    Result, Show,
+};
+use ast::{BigInt, BigUint};
+use num_traits::ToPrimitive;
+use std::{borrow::Cow, rc::Rc};
+
+/// The value of an `Option`, or no reduction.
+macro_rules! some {
+    ($e:expr) => {
+        match $e {
+            Some(v) => v,
+            None => return Ok(None),
+        }
+    };
+}
+
+/// Whether `name` is a builtin, when it is not bound.
+pub fn is_builtin(name: &str) -> bool {
+    arity(name).is_some()
+        || matches!(
+            name,
+            "Bool" | "True" | "False" | "Natural" | "Integer" | "Double" | "Bytes" | "Text" | "List" | "Optional"
+                | "Some" | "None" | "Type" | "Kind" | "Sort"
+        )
+}
+
+/// The number of arguments a builtin takes before it reduces.
+pub fn arity(name: &str) -> Option<usize> {
+    Some(match name {
+        "Natural/isZero" | "Natural/even" | "Natural/odd" | "Natural/toInteger" | "Natural/show"
+        | "Natural/build" | "Integer/toDouble" | "Integer/show" | "Integer/negate"
+        | "Integer/clamp" | "Double/show" | "Text/show" => 1,
+        "Natural/subtract" | "List/build" | "List/length" | "List/head" | "List/last"
+        | "List/indexed" | "List/reverse" => 2,
+        "Text/replace" => 3,
+        "Natural/fold" => 4,
+        "List/fold" => 5,
+        _ => return None,
+    })
+}
+
+/// Reduce `app`, if it is a saturated application of a builtin and the
+/// arguments allow it.
+pub fn reduce<'a, 'i>(ctx: &Context, app: &V<'a, 'i>) -> Result<Option<V<'a, 'i>>> {
+    let mut args = Vec::new();
+    let mut head = app;
+    while let Value::App(f, x) = &**head {
+        args.push(x);
+        head = f;
+    }
+    let name = match &**head {
+        Value::Builtin(name) => unquote(name),
+        _ => return Ok(None),
+    };
+    if arity(name) != Some(args.len()) {
+        return Ok(None);
+    }
+    args.reverse();
+
+    let value = match (name, args.as_slice()) {
+        ("Natural/isZero", [n]) => boolean(*some!(natural(n)) == BigUint::default()),
+        ("Natural/even", [n]) => boolean(!some!(natural(n)).bit(0)),
+        ("Natural/odd", [n]) => boolean(some!(natural(n)).bit(0)),
+        ("Natural/toInteger", [n]) => Value::Integer(BigInt::from(some!(natural(n)).clone())),
+        ("Natural/show", [n]) => literal(some!(natural(n)).to_string()),
+        ("Natural/subtract", [a, b]) => match (natural(a), natural(b)) {
+            (Some(a), Some(b)) if b >= a => Value::Natural(b - a),
+            (Some(_), Some(_)) => Value::Natural(<_>::default()),
+            (Some(a), _) if *a == BigUint::default() => return Ok(Some((*b).clone())),
+            (_, Some(b)) if *b == BigUint::default() => Value::Natural(<_>::default()),
+            _ if conv(ctx, a, b) => Value::Natural(<_>::default()),
+            _ => return Ok(None),
+        },
+        ("Natural/build", [g]) => {
+            // `Natural/build (Natural/fold n)` is `n`
+            if let Some([n]) = applied(g, "Natural/fold").as_deref() {
+                return Ok(Some(n.clone()));
+            }
+            let natural = Rc::new(Value::Builtin("Natural"));
+            let succ = Closure::native(|ctx, x| operation(ctx, "+", x, Rc::new(Value::Natural(1u8.into()))));
+            let succ = Rc::new(Value::Lam("x", Some(natural.clone()), succ));
+            let zero = Rc::new(Value::Natural(<_>::default()));
+            return apply_all(ctx, (*g).clone(), vec![natural, succ, zero]).map(Some);
+        }
+        ("Natural/fold", [n, _, succ, zero]) => {
+            let count = some!(some!(natural(n)).to_u64());
+            let mut acc = (*zero).clone();
+            for _ in 0..count {
+                acc = apply(ctx, (*succ).clone(), acc)?;
+            }
+            return Ok(Some(acc));
+        }
+        ("Integer/toDouble", [n]) => Value::Double(some!(some!(integer(n)).to_f64())),
+        ("Integer/show", [n]) => literal(format!("{:+}", some!(integer(n)))),
+        ("Integer/negate", [n]) => Value::Integer(-some!(integer(n)).clone()),
+        ("Integer/clamp", [n]) => Value::Natural(some!(integer(n)).to_biguint().unwrap_or_default()),
+        ("Double/show", [d]) => match ***d {
+            Value::Double(d) => literal(Show(&ast::Term::Double(d)).to_string()),
+            _ => return Ok(None),
+        },
+        ("Text/show", [t]) => {
+            let literal_ = ast::Term::Text(1, ast::deq((Cow::Owned(some!(text_value(t))), None)));
+            literal(Show(&literal_).to_string())
+        }
+        ("Text/replace", [needle, replacement, haystack]) => {
+            let needle = some!(text_value(needle));
+            let value = some!(text_value(haystack));
+            if needle.is_empty() {
+                return Ok(Some((*haystack).clone()));
+            }
+            // The replacement may be abstract: splice it in as interpolations.
+            let mut chunks = Vec::new();
+            for (i, piece) in value.split(needle.as_str()).enumerate() {
+                if i > 0 {
+                    chunks.push((Cow::Borrowed(""), Some((*replacement).clone())));
+                }
+                chunks.push((Cow::Owned(piece.to_owned()), None));
+            }
+            return Ok(Some(text(chunks)));
+        }
+        ("List/build", [a, g]) => {
+            // `List/build a (List/fold a xs)` is `xs`
+            if let Some([_, xs]) = applied(g, "List/fold").as_deref() {
+                return Ok(Some(xs.clone()));
+            }
+            let list_a = Rc::new(Value::App(Rc::new(Value::Builtin("List")), (*a).clone()));
+            let tail = list_a.clone();
+            let cons = Closure::native(move |_, x| {
+                let append = Closure::native(move |ctx, xs| {
+                    operation(ctx, "#", Rc::new(Value::List(vec![x.clone()])), xs)
+                });
+                Ok(Rc::new(Value::Lam("xs", Some(tail.clone()), append)))
+            });
+            let cons = Rc::new(Value::Lam("a", Some((*a).clone()), cons));
+            let nil = Rc::new(Value::EmptyList(list_a.clone()));
+            return apply_all(ctx, (*g).clone(), vec![list_a, cons, nil]).map(Some);
+        }
+        ("List/fold", [_, l, _, cons, nil]) => {
+            let mut acc = (*nil).clone();
+            for item in some!(list(l)).iter().rev() {
+                acc = apply_all(ctx, (*cons).clone(), vec![item.clone(), acc])?;
+            }
+            return Ok(Some(acc));
+        }
+        ("List/length", [_, l]) => Value::Natural(some!(list(l)).len().into()),
+        ("List/head", [a, l]) => optional(a, some!(list(l)).first()),
+        ("List/last", [a, l]) => optional(a, some!(list(l)).last()),
+        ("List/indexed", [a, l]) => {
+            let items = some!(list(l));
+            if items.is_empty() {
+                let record = Value::RecordType(vec![
+                    ("index", Rc::new(Value::Builtin("Natural"))),
+                    ("value", (*a).clone()),
+                ]);
+                let list_record = Value::App(Rc::new(Value::Builtin("List")), Rc::new(record));
+                Value::EmptyList(Rc::new(list_record))
+            } else {
+                let items = items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, value)| {
+                        let index = Rc::new(Value::Natural(index.into()));
+                        Rc::new(Value::Record(vec![("index", index), ("value", value.clone())]))
+                    })
+                    .collect();
+                Value::List(items)
+            }
+        }
+        ("List/reverse", [_, l]) => match some!(list(l)) {
+            [] => return Ok(Some((*l).clone())),
+            items => Value::List(items.iter().rev().cloned().collect()),
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(Rc::new(value)))
+}
+
+/// The arguments `v` applies the builtin `name` to, if it does.
+fn applied<'a, 'i>(v: &V<'a, 'i>, name: &str) -> Option<Vec<V<'a, 'i>>> {
+    let mut args = Vec::new();
+    let mut head = v;
+    while let Value::App(f, x) = &**head {
+        args.push(x.clone());
+        head = f;
+    }
+    match &**head {
+        Value::Builtin(b) if unquote(b) == name => {
+            args.reverse();
+            Some(args)
+        }
+        _ => None,
+    }
+}
+
+/// Apply `f` to `args`, left to right.
+fn apply_all<'a, 'i>(ctx: &Context, f: V<'a, 'i>, args: Vec<V<'a, 'i>>) -> Result<V<'a, 'i>> {
+    args.into_iter().try_fold(f, |f, x| apply(ctx, f, x))
+}
+
+fn natural<'v>(v: &'v Value) -> Option<&'v BigUint> {
+    match v {
+        Value::Natural(n) => Some(n),
+        _ => None,
+    }
+}
+
+fn integer<'v>(v: &'v Value) -> Option<&'v BigInt> {
+    match v {
+        Value::Integer(n) => Some(n),
+        _ => None,
+    }
+}
+
+/// The items of a list literal, annotated or not.
+fn list<'v, 'a, 'i>(v: &'v Value<'a, 'i>) -> Option<&'v [V<'a, 'i>]> {
+    match v {
+        Value::List(items) => Some(items),
+        Value::EmptyList(_) => Some(&[]),
+        _ => None,
+    }
+}
+
+/// The value of a text literal without interpolations.
+fn text_value(v: &Value) -> Option<String> {
+    match v {
+        Value::Text(chunks) => {
+            let mut value = String::new();
+            for (chunk, hole) in chunks {
+                if hole.is_some() {
+                    return None;
+                }
+                value.push_str(chunk);
+            }
+            Some(value)
+        }
+        _ => None,
+    }
+}
+
+fn literal<'a, 'i>(value: String) -> Value<'a, 'i> {
+    Value::Text(vec![(Cow::Owned(value), None)])
+}
+
+fn boolean<'a, 'i>(b: bool) -> Value<'a, 'i> {
+    Value::Builtin(if b { "True" } else { "False" })
+}
+
+fn optional<'a, 'i>(a: &V<'a, 'i>, item: Option<&V<'a, 'i>>) -> Value<'a, 'i> {
+    match item {
+        Some(item) => Value::App(Rc::new(Value::Builtin("Some")), item.clone()),
+        None => Value::App(Rc::new(Value::Builtin("None")), a.clone()),
+    }
+}
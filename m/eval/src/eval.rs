@@ -1,10 +1,11 @@
-use super::{bail, ASubstitution, AsImm, Result, Set, Show, SymTable};
-use ast::IsList;
-use std::mem;
+use super::{builtins, Error, Result, Show};
+use ast::{BigInt, BigUint, Ident, Location, Spans};
+use error::{EvalError, TypeError};
+use std::{borrow::Cow, cell::Cell, mem, rc::Rc};
 
-pub type Ctx<'i> = &'i mut Context<'i>;
+pub type Ctx<'c> = &'c mut Context<'c>;
 
-pub fn ctx<'i>() -> Context<'i> {
+pub fn ctx<'s>() -> Context<'s> {
     Context::new()
 }
 
@@ -12,640 +13,947 @@ pub trait Eval<'i> {
     fn eval(&mut self, ctx: Ctx<'i>) -> Result<Ctx<'i>>;
 }
 
-fn in_place_term1<'i>(mut ctx: Ctx<'i>, a: &mut ast::Term1<'i>) -> R<'i> {
-    let mut e: ast::Expr = mem::take(a).into();
-    ctx = e.eval(ctx)?;
+/// Normalize by evaluating into the semantic domain and quoting back.
+impl<'i> Eval<'i> for ast::Expr<'i> {
+    fn eval(&mut self, ctx: Ctx<'i>) -> Result<Ctx<'i>> {
+        let expr = mem::take(self);
+        let value = eval_expr(ctx, &Env::default(), &expr)?;
+        *self = quote(ctx, &mut Vec::new(), &value)?;
+        Ok(ctx)
+    }
+}
 
-    *a = match e {
-        ast::Expr::Term1(t1) => t1,
-        e => ast::Term1::Term(ast::Term::Expr(ctx.rebox(e))),
-    };
-    Ok(ctx)
+/// The normal form of `expr`, whose free variables are bound by `names`
+/// (outermost first) to unknown values.
+pub fn normalize<'i>(names: &[Ident<'i>], expr: &ast::Expr<'i>) -> Result<ast::Expr<'i>> {
+    let ctx = Context::new();
+    let (env, mut scope) = open(&ctx, names);
+    let value = eval_expr(&ctx, &env, expr)?;
+    quote(&ctx, &mut scope, &value)
 }
 
-fn in_place_term<'i>(mut ctx: Ctx<'i>, a: &mut ast::Term<'i>) -> R<'i> {
-    let mut e: ast::Expr = mem::take(a).into();
-    ctx = e.eval(ctx)?;
+/// Whether `a` and `b` are judgmentally equal under `names`: equivalent once
+/// evaluated, as `conv` decides.
+pub fn equivalent<'i>(names: &[Ident<'i>], a: &ast::Expr<'i>, b: &ast::Expr<'i>) -> bool {
+    let ctx = Context::new();
+    let (env, _) = open(&ctx, names);
+    match (eval_expr(&ctx, &env, a), eval_expr(&ctx, &env, b)) {
+        (Ok(a), Ok(b)) => conv(&ctx, &a, &b),
+        _ => false,
+    }
+}
 
-    *a = match e {
-        ast::Expr::Term1(ast::Term1::Term(t)) => t,
-        e => ast::Term::Expr(ctx.rebox(e)),
-    };
-    Ok(ctx)
+/// An environment binding `names` to new variables, and the ids of these.
+fn open<'a, 'i>(ctx: &Context, names: &[Ident<'i>]) -> (Env<'a, 'i>, Vec<(Ident<'i>, usize)>) {
+    let mut env = Env::default();
+    let mut scope = Vec::with_capacity(names.len());
+    for name in names {
+        let var = ctx.fresh(name);
+        if let Value::Var(_, id) = *var {
+            scope.push((*name, id));
+        }
+        env = env.extend(name, var);
+    }
+    (env, scope)
 }
 
-impl<'i> Eval<'i> for ast::Expr<'i> {
-    fn eval(&mut self, mut ctx: Ctx<'i>) -> Result<Ctx<'i>> {
-        let mut tmp = <_>::default();
-        mem::swap(self, &mut tmp);
+/// The context of an evaluation.
+#[derive(Default)]
+pub struct Context<'s> {
+    /// The id of the next variable introduced to look under a binder.
+    fresh: Cell<usize>,
+    spans: Option<&'s Spans<'s>>,
+}
 
-        let value = &mut tmp;
+impl<'s> Context<'s> {
+    /// Create an empty evaluation context.
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        use ast::{Expr::*, Term::*, Term1::*};
-        let norm = match value {
-            Let(defs, val) => {
-                ctx.sym_table.mark_scope();
+    /// Locate evaluation errors using the spans the tree was parsed with.
+    pub fn with_spans(mut self, spans: &'s Spans<'s>) -> Self {
+        self.spans = Some(spans);
+        self
+    }
 
-                for (name, typ, val) in defs {
-                    ctx = typ.eval(ctx)?;
-                    ctx = val.eval(ctx)?;
+    fn locate<T>(&self, node: &T) -> Option<Location> {
+        let spans = self.spans?;
+        spans.get(node).map(|span| spans.locate(span))
+    }
 
-                    let typ = match typ {
-                        Some(b) => Some(mem::take(b.as_mut())),
-                        _ => None,
-                    };
-                    let val = ctx.unbox(val);
+    fn fresh<'a, 'i>(&self, name: Ident<'i>) -> V<'a, 'i> {
+        let id = self.fresh.get();
+        self.fresh.set(id + 1);
+        Rc::new(Value::Var(name, id))
+    }
+}
 
-                    ctx.sym_table.enter_scope();
-                    ctx.sym_table.add(name, typ, Some(val));
-                }
+/// An evaluated expression.
+///
+/// Functions are closures over the environment they were evaluated in, so
+/// that applying one evaluates its body once, with the argument bound. What
+/// can not be reduced (for being stuck on a variable) is kept as a neutral
+/// value, which quotes back to the matching syntax.
+pub enum Value<'a, 'i> {
+    Lam(Ident<'i>, Option<V<'a, 'i>>, Closure<'a, 'i>),
+    Pi(Option<Ident<'i>>, V<'a, 'i>, Closure<'a, 'i>),
+    /// A variable introduced to look under a binder, by its unique id.
+    Var(Ident<'i>, usize),
+    Builtin(Ident<'i>),
+    Natural(BigUint),
+    Integer(BigInt),
+    Double(f64),
+    Bytes(Vec<u8>),
+    /// The chunks of a text literal, each followed by an interpolation.
+    Text(Vec<Chunk<'a, 'i>>),
+    List(Vec<V<'a, 'i>>),
+    /// An empty list, with its type.
+    EmptyList(V<'a, 'i>),
+    Record(Fields<'a, 'i>),
+    RecordType(Fields<'a, 'i>),
+    Union(Vec<(Ident<'i>, Option<V<'a, 'i>>)>),
+    App(V<'a, 'i>, V<'a, 'i>),
+    Operation(&'i str, V<'a, 'i>, V<'a, 'i>),
+    If(V<'a, 'i>, V<'a, 'i>, V<'a, 'i>),
+    Field(V<'a, 'i>, Ident<'i>),
+    Project(V<'a, 'i>, Vec<Ident<'i>>),
+    ProjectBy(V<'a, 'i>, V<'a, 'i>),
+    /// `merge handlers t`, and its annotation.
+    Merge(Fields<'a, 'i>, V<'a, 'i>, Option<V<'a, 'i>>),
+    ToMap(V<'a, 'i>, Option<V<'a, 'i>>),
+    ShowConstructor(V<'a, 'i>),
+    With(V<'a, 'i>, Vec<Ident<'i>>, V<'a, 'i>),
+    Assert(V<'a, 'i>),
+    /// Imports and embedded code, left as they are.
+    Syntax(&'a ast::Term<'i>),
+}
 
-                ctx = val.eval(ctx)?;
-                ctx.sym_table.return_to_marked_scope();
-                Ok(Some(val.as_mut()))
-            }
-            Term1(Term(Var(name, scope))) => {
-                log::trace!("{:4} eval Var {} @{}", line!(), name, scope,);
+pub type V<'a, 'i> = Rc<Value<'a, 'i>>;
+pub type Fields<'a, 'i> = Vec<(Ident<'i>, V<'a, 'i>)>;
+pub type Chunk<'a, 'i> = (Cow<'i, str>, Option<V<'a, 'i>>);
 
-                let info = ctx.sym_table.lookup(name, *scope)?;
+/// The values of the variables in scope, innermost first.
+#[derive(Clone, Default)]
+pub struct Env<'a, 'i>(Option<Rc<(Ident<'i>, V<'a, 'i>, Env<'a, 'i>)>>);
 
-                match &info.value {
-                    None => Ok(None), // thunk value - ok
-                    Some(val) => Err(Some(val.to_owned())),
-                }
-            }
-            Term1(Term(TypeRecord(fields))) => {
-                for (_, val) in fields {
-                    ctx = val.eval(ctx)?;
+impl<'a, 'i> Env<'a, 'i> {
+    fn extend(&self, name: Ident<'i>, value: V<'a, 'i>) -> Self {
+        Self(Some(Rc::new((name, value, self.clone()))))
+    }
+
+    /// The value of `name@idx`, or the index it has outside of the scope.
+    fn lookup(&self, name: &str, mut idx: u16) -> std::result::Result<V<'a, 'i>, u16> {
+        let mut env = self;
+        while let Some(entry) = &env.0 {
+            let (n, value, outer) = entry.as_ref();
+            if *n == name {
+                if idx == 0 {
+                    return Ok(value.clone());
                 }
-                Ok(None)
+                idx -= 1;
             }
-            Term1(Term(TypeEnum(fields))) => {
-                for (_, typ) in fields {
-                    ctx = typ.eval(ctx)?;
-                }
-                Ok(None)
+            env = outer;
+        }
+        Err(idx)
+    }
+}
+
+/// The body of a function, waiting for its argument.
+#[derive(Clone)]
+pub enum Closure<'a, 'i> {
+    Syntax(Env<'a, 'i>, Ident<'i>, &'a ast::Expr<'i>),
+    /// A function built by a builtin.
+    #[allow(clippy::type_complexity)]
+    Native(Rc<dyn Fn(&Context, V<'a, 'i>) -> Result<V<'a, 'i>> + 'a>),
+}
+
+impl<'a, 'i> Closure<'a, 'i> {
+    pub fn native<F>(f: F) -> Self
+    where
+        F: Fn(&Context, V<'a, 'i>) -> Result<V<'a, 'i>> + 'a,
+    {
+        Self::Native(Rc::new(f))
+    }
+
+    pub fn apply(&self, ctx: &Context, x: V<'a, 'i>) -> Result<V<'a, 'i>> {
+        match self {
+            Self::Syntax(env, name, body) => eval_expr(ctx, &env.extend(name, x), body),
+            Self::Native(f) => f(ctx, x),
+        }
+    }
+}
+
+fn eval_expr<'a, 'i>(ctx: &Context, env: &Env<'a, 'i>, e: &'a ast::Expr<'i>) -> Result<V<'a, 'i>> {
+    use ast::Expr::*;
+
+    match e {
+        Term1(t1) => eval_term1(ctx, env, t1),
+        Let(defs, body) => {
+            // Each definition is evaluated once, and shared by its uses.
+            let mut env = env.clone();
+            for (name, _, val) in defs {
+                let value = eval_expr(ctx, &env, val)?;
+                env = env.extend(name, value);
             }
-            Term1(Term(Record(fields))) => {
-                for (_, val) in fields {
-                    ctx = val.eval(ctx)?;
+            eval_expr(ctx, &env, body)
+        }
+        Lambda(name, typ, body) => {
+            let typ = match typ {
+                Some(typ) => Some(eval_expr(ctx, env, typ)?),
+                None => None,
+            };
+            Ok(Rc::new(Value::Lam(name, typ, Closure::Syntax(env.clone(), name, body))))
+        }
+    }
+}
+
+fn eval_term1<'a, 'i>(ctx: &Context, env: &Env<'a, 'i>, t: &'a ast::Term1<'i>) -> Result<V<'a, 'i>> {
+    term1_value(ctx, env, t).map_err(|e| e.at(ctx.locate(t)))
+}
+
+fn term1_value<'a, 'i>(ctx: &Context, env: &Env<'a, 'i>, t: &'a ast::Term1<'i>) -> Result<V<'a, 'i>> {
+    use ast::Term1::*;
+
+    match t {
+        Term(t) => eval_term(ctx, env, t),
+        Evaluation(f, x) => {
+            let f = eval_term1(ctx, env, f)?;
+            let x = eval_term(ctx, env, x)?;
+            apply(ctx, f, x)
+        }
+        Arrow(name, a, b) => {
+            let a = eval_expr(ctx, env, a)?;
+            let closure = Closure::Syntax(env.clone(), name.unwrap_or("_"), b);
+            Ok(Rc::new(Value::Pi(*name, a, closure)))
+        }
+        With(t, path, val) => {
+            let t = eval_term1(ctx, env, t)?;
+            let val = eval_term1(ctx, env, val)?;
+            let path: Vec<_> = path.iter().copied().collect();
+            with(ctx, t, &path, val)
+        }
+        Operation(a, op, b) => {
+            let a = eval_term1(ctx, env, a)?;
+            let b = eval_term1(ctx, env, b)?;
+            operation(ctx, op, a, b)
+        }
+        IfThenElse(c, a, b) => {
+            let c = eval_expr(ctx, env, c)?;
+            match &*c {
+                Value::Builtin("True") => eval_expr(ctx, env, a),
+                Value::Builtin("False") => eval_expr(ctx, env, b),
+                c_ if is_neutral(c_) => {
+                    let a = eval_expr(ctx, env, a)?;
+                    let b = eval_expr(ctx, env, b)?;
+                    Ok(match (&*a, &*b) {
+                        (Value::Builtin("True"), Value::Builtin("False")) => c,
+                        _ if conv(ctx, &a, &b) => a,
+                        _ => Rc::new(Value::If(c, a, b)),
+                    })
                 }
-                Ok(None)
+                _ => Err(EvalError::InvalidPredicate(show_value(ctx, &c)).into()),
             }
-            Term1(Term(List(fields))) => {
-                for val in fields {
-                    ctx = val.eval(ctx)?;
+        }
+        Assert(equiv) => {
+            let typ = eval_expr(ctx, env, equiv)?;
+            match &*typ {
+                Value::Operation("≡" | "===", a, b) if conv(ctx, a, b) => Ok(Rc::new(Value::Assert(typ))),
+                Value::Operation("≡" | "===", a, b) => {
+                    let err = Error::from(EvalError::AssertionFailed(show_value(ctx, a), show_value(ctx, b)));
+                    Err(err.at(ctx.locate(equiv.as_ref())))
                 }
-                Ok(None)
-            }
-            Term1(Term(Integer(_))) => Ok(None),
-            Term1(Term(Double(_))) => Ok(None),
-            Term1(Term(Text(_, _))) => Ok(None),
-            Term1(Term(Import {
-                path: "missing", ..
-            })) => Err(Some(ast::const_0_expr())),
-            Term1(Term(Expr(e))) => {
-                ctx = e.eval(ctx)?;
-                let e = ctx.unbox(e);
-
-                // Replace with inner expr
-                Err(Some(e))
+                _ => Err(TypeError::NotAnEquivalence(show_value(ctx, &typ)).into()),
             }
-            Term1(Term(Project(1, t, selectors))) => {
-                log::trace!(
-                    "{:4} eval Project(1) {} . {:?}",
-                    line!(),
-                    Show(t.as_ref()),
-                    selectors
-                );
-                ctx = in_place_term(ctx, t)?;
-                let mut names = Set::new();
-                for name in selectors {
-                    // DO NOT Eval! These are declaring identifiers, not to be looked up.
-                    // (dispite being a term in ast, this is parsing bogus).
-                    //
-                    //ctx = in_place_term1(ctx, name)?;
-                    //
-                    // Simply extract to names:
-                    match name {
-                        Term(Var(n, _)) => {
-                            names.insert(*n);
-                        }
-                        o => panic!("Projection selectors must be identifiers: {:?}", o),
-                    }
+        }
+        Ascribe(a, typ) => {
+            // Annotations are dropped, unless they are needed for the type.
+            let a = eval_term1(ctx, env, a)?;
+            Ok(match &*a {
+                Value::List(items) if items.is_empty() => Rc::new(Value::EmptyList(eval_expr(ctx, env, typ)?)),
+                Value::Merge(handlers, t, None) => {
+                    let typ = eval_expr(ctx, env, typ)?;
+                    Rc::new(Value::Merge(handlers.clone(), t.clone(), Some(typ)))
                 }
-                match t.as_mut() {
-                    Record(fields) => {
-                        fields.retain(|(name, _)| {
-                            name.front().map(|p| names.contains(p)).unwrap_or(false)
-                        });
-                        let mut t = mem::take(t);
-                        let t = ctx.unbox(&mut t);
-                        Err(Some(Term1(Term(t))))
-                    }
-                    t if ctx.is_thunk_term(t)? => Ok(None),
-                    o => panic!(
-                        "Projection term must be a type record (or thunk term): {:?}",
-                        o
-                    ),
+                Value::ToMap(t, None) => Rc::new(Value::ToMap(t.clone(), Some(eval_expr(ctx, env, typ)?))),
+                _ => a,
+            })
+        }
+        // Alternatives are chosen between by the resolver.
+        ImportAlt(_, _) => Err(EvalError::Unsupported(show(t)).into()),
+        Construct(t, fields) => {
+            // `T::r` is `T.default ⫽ r`
+            let t = eval_term1(ctx, env, t)?;
+            let default = field(ctx, t, "default")?;
+            let fields = eval_fields(ctx, env, fields)?;
+            operation(ctx, "⫽", default, Rc::new(Value::Record(fields)))
+        }
+    }
+}
+
+fn eval_term<'a, 'i>(ctx: &Context, env: &Env<'a, 'i>, t: &'a ast::Term<'i>) -> Result<V<'a, 'i>> {
+    term_value(ctx, env, t).map_err(|e| e.at(ctx.locate(t)))
+}
+
+fn term_value<'a, 'i>(ctx: &Context, env: &Env<'a, 'i>, t: &'a ast::Term<'i>) -> Result<V<'a, 'i>> {
+    use ast::Term::*;
+
+    Ok(Rc::new(match t {
+        Natural(n) => Value::Natural(n.clone()),
+        Integer(n) => Value::Integer(n.clone()),
+        Double(d) => Value::Double(*d),
+        Bytes(bytes) => Value::Bytes(bytes.clone()),
+        Var(name, idx) => match env.lookup(name, *idx) {
+            Ok(value) => return Ok(value),
+            Err(0) if builtins::is_builtin(unquote(name)) => Value::Builtin(name),
+            Err(_) => return Err(EvalError::UnboundVariable(name.to_string(), *idx).into()),
+        },
+        FieldAccess(t, name) => return field(ctx, eval_term(ctx, env, t)?, name),
+        Project(1, t, selectors) => {
+            let mut names = Vec::new();
+            for s in selectors {
+                match s {
+                    // Declaring labels, not variables to be looked up.
+                    ast::Term1::Term(Var(n, _)) => names.push(*n),
+                    o => return Err(EvalError::InvalidSelector(show(o)).into()),
                 }
             }
-            Term1(Term(FieldAccess(t, name))) => {
-                ctx = in_place_term(ctx, t)?;
-                match t.as_mut() {
-                    Record(fields) => {
-                        fields.retain(|(path, _)| path.front().map(|s| name == s).unwrap_or(false));
-
-                        let mut parafields = mem::take(fields);
-                        let mut emerged = None;
-                        let mut retained = ast::Deq::new();
-
-                        for (path, field) in &mut parafields {
-                            ctx = field.eval(ctx)?;
-
-                            path.pop_front();
-                            if path.is_empty() {
-                                emerged = Some(field);
-                            } else {
-                                retained.push_back((mem::take(path), mem::take(field)));
-                            }
-                        }
-
-                        if let Some(inner) = emerged {
-                            let mut inner = ctx.unbox(inner);
-                            // replace with inner value
-                            match &mut inner {
-                                Term1(Term(Record(inner_fields))) => {
-                                    inner_fields.append(&mut retained);
-                                }
-                                _ if retained.is_empty() => {}
-                                _ => panic! {
-                                    "How to access {} from {}?",
-                                    name, Show(t.as_ref())
-                                },
-                            }
-                            Err(Some(inner))
-                        } else {
-                            Err(Some(Term1(Term(Record(retained)))))
-                        }
-                    }
-                    TypeEnum(_) => Ok(None),
-                    t if ctx.is_thunk_term(t)? => Ok(None),
-                    other => {
-                        let is_thunk = ctx.is_thunk_term(&other)?;
-                        let as_expr = Term1(Term(mem::take(other)));
-                        panic!(
-                            "Field Access of: (thunk={}) .{} {}\n{:?}",
-                            is_thunk,
-                            name,
-                            Show(&as_expr),
-                            as_expr,
-                        );
-                    }
+            return project(ctx, eval_term(ctx, env, t)?, names);
+        }
+        Project(_, t, selector) => {
+            let t = eval_term(ctx, env, t)?;
+            let s = match selector.front() {
+                Some(s) if selector.len() == 1 => eval_term1(ctx, env, s)?,
+                _ => {
+                    let selector = selector.iter().map(show).collect::<Vec<_>>();
+                    return Err(EvalError::InvalidSelector(selector.join(", ")).into());
                 }
+            };
+            match &*s {
+                // Projection by the labels of the type
+                Value::RecordType(fields) => return project(ctx, t, fields.iter().map(|(n, _)| *n).collect()),
+                s_ if is_neutral(s_) => Value::ProjectBy(t, s),
+                _ => return Err(EvalError::InvalidSelector(show_value(ctx, &s)).into()),
             }
-            Term1(Ascribe(a, b)) => {
-                ctx = in_place_term1(ctx, a)?;
-                ctx = b.eval(ctx)?;
-                Ok(None)
-            }
-            Term1(Operation(a, op, b)) => {
-                ctx = in_place_term1(ctx, a)?;
-                ctx = in_place_term1(ctx, b)?;
-                match (*op, a.as_mut(), b.as_mut()) {
-                    ("⫽" | "//", Term(Record(fields_a)), Term(Record(fields_b))) => {
-                        fields_a.append(fields_b);
-                        Err(Some(Term1(ctx.unbox(a))))
-                    }
-                    ("∧" | "/\\", Term(Record(fields_a)), Term(Record(fields_b))) => {
-                        let mut names = Set::new();
-                        let a_names = fields_a.iter().map(|(p, _)| p.front());
-                        let b_names = fields_b.iter().map(|(p, _)| p.front());
-                        for n in a_names.chain(b_names) {
-                            if !names.insert(n) {
-                                bail!("{:4} ∧ field collision: {:?}", line!(), n);
-                            }
-                        }
-                        fields_a.append(fields_b);
-                        Err(Some(Term1(ctx.unbox(a))))
-                    }
-                    ("≡" | "===", expr_a, expr_b) => {
-                        let _ = (expr_a, expr_b);
-                        log::warn!("{:4} STUB ≡", line!());
-                        Ok(None)
-                    }
-                    ("#", Term(List(a)), Term(List(b))) => {
-                        a.append(b);
-                        Err(Some(Term1(Term(List(mem::take(a))))))
-                    }
-                    ("#", Term(List(a)), Ascribe(b, _)) if b.is_list() => {
-                        let b = b.get_list_mut();
-                        a.append(b);
-                        Err(Some(Term1(Term(List(mem::take(a))))))
-                    }
-                    ("#", Ascribe(a, _), Term(List(b))) if a.is_list() => {
-                        let a = a.get_list_mut();
-                        a.append(b);
-                        Err(Some(Term1(Term(List(mem::take(a))))))
-                    }
-                    ("#", Ascribe(a, _), Ascribe(b, _)) if a.is_list() && b.is_list() => {
-                        let a = a.get_list_mut();
-                        let b = b.get_list_mut();
-                        a.append(b);
-                        Err(Some(Term1(Term(List(mem::take(a))))))
-                    }
-                    ("*", &mut Term(Integer(a)), &mut Term(Integer(b))) => {
-                        Err(Some(Term1(Term(Integer(a * b)))))
-                    }
-                    ("*", &mut Term(Double(a)), &mut Term(Integer(b))) => {
-                        Err(Some(Term1(Term(Double(a * b as f32)))))
-                    }
-                    ("*", &mut Term(Integer(a)), &mut Term(Double(b))) => {
-                        Err(Some(Term1(Term(Double(a as f32 * b)))))
-                    }
-                    ("*", &mut Term(Double(a)), &mut Term(Double(b))) => {
-                        Err(Some(Term1(Term(Double(a * b)))))
-                    }
-                    ("+", &mut Term(Integer(a)), &mut Term(Integer(b))) => {
-                        Err(Some(Term1(Term(Integer(a + b)))))
-                    }
-                    ("+", &mut Term(Double(a)), &mut Term(Integer(b))) => {
-                        Err(Some(Term1(Term(Double(a + b as f32)))))
-                    }
-                    ("+", &mut Term(Integer(a)), &mut Term(Double(b))) => {
-                        Err(Some(Term1(Term(Double(a as f32 + b)))))
-                    }
-                    ("+", &mut Term(Double(a)), &mut Term(Double(b))) => {
-                        Err(Some(Term1(Term(Double(a + b)))))
-                    }
-                    ("-", &mut Term(Integer(a)), &mut Term(Integer(b))) => {
-                        Err(Some(Term1(Term(Integer(a - b)))))
-                    }
-                    ("-", &mut Term(Double(a)), &mut Term(Integer(b))) => {
-                        Err(Some(Term1(Term(Double(a - b as f32)))))
-                    }
-                    ("-", &mut Term(Integer(a)), &mut Term(Double(b))) => {
-                        Err(Some(Term1(Term(Double(a as f32 - b)))))
-                    }
-                    ("-", &mut Term(Double(a)), &mut Term(Double(b))) => {
-                        Err(Some(Term1(Term(Double(a - b)))))
-                    }
-                    ("/", &mut Term(Integer(a)), &mut Term(Integer(b))) => {
-                        Err(Some(Term1(Term(Integer(a / b)))))
-                    }
-                    ("/", &mut Term(Double(a)), &mut Term(Integer(b))) => {
-                        Err(Some(Term1(Term(Double(a / b as f32)))))
-                    }
-                    ("/", &mut Term(Integer(a)), &mut Term(Double(b))) => {
-                        Err(Some(Term1(Term(Double(a as f32 / b)))))
-                    }
-                    ("/", &mut Term(Double(a)), &mut Term(Double(b))) => {
-                        Err(Some(Term1(Term(Double(a / b)))))
-                    }
-                    ("++", Term(Text(1, a)), Term(Text(1, b))) => {
-                        a.append(b);
-                        Err(Some(Term1(Term(Text(1, mem::take(a))))))
-                    }
-                    (_, a, b) if ctx.is_thunk_term1(&a)? || ctx.is_thunk_term1(&b)? => Ok(None),
-                    o => panic!("Invalid operation: {:?}", o),
-                }
+        }
+        Text(_, entries) => {
+            let mut chunks = Vec::with_capacity(entries.len());
+            for (chunk, hole) in entries {
+                let hole = match hole {
+                    Some(hole) => Some(eval_expr(ctx, env, hole)?),
+                    None => None,
+                };
+                chunks.push((chunk.clone(), hole));
             }
-            Term1(Evaluation(f, x)) => {
-                ctx = in_place_term1(ctx, f)?;
-                ctx = in_place_term(ctx, x)?;
-                log::trace!(
-                    "{:4} eval Evaluation( {} , {} )",
-                    line!(),
-                    Show(f.as_ref()),
-                    Show(&*x)
-                );
-
-                match (f.as_mut(), x) {
-                    (Term(Expr(e)), x) => match e.as_mut() {
-                        Lambda(n, t, b) => {
-                            let x: ast::Term = mem::take(x);
-
-                            ctx.sym_table.enter_scope();
-                            let mut x = ast::Expr::Term1(ast::Term1::Term(x));
-                            x.commit_name(n);
-                            ctx.sym_table.add(n, None, Some(x));
-
-                            ctx = t.eval(ctx)?;
-                            ctx = b.eval(ctx)?;
-                            ctx.sym_table.exit_scope();
-
-                            b.free_name(n);
-
-                            Err(Some(ctx.unbox(b)))
-                        }
-                        Term1(Evaluation(f, _)) if ctx.is_thunk_term1(f.as_ref())? => Ok(None),
-                        o => panic!(
-                            "After-evaluation non lambda expression in substitution: {:?}",
-                            o
-                        ),
-                    },
-                    (Term(FieldAccess(t, _)), _) => match t.as_mut() {
-                        t if ctx.is_thunk_term(t)? => Ok(None),
-                        o => panic!("After-evaluation non field accessible: {:?}", o),
-                    },
-                    (t, _) if ctx.is_thunk_term1(t)? => Ok(None),
-                    other => panic!("How to Evaluation {:?}", other),
-                }
-                .map(|r| {
-                    log::trace!("{:4} eval Evaluation => {:?}", line!(), r);
-                    r
-                })
+            return Ok(text(chunks));
+        }
+        List(items) => Value::List(items.iter().map(|v| eval_expr(ctx, env, v)).collect::<Result<_>>()?),
+        Record(fields) => Value::Record(eval_fields(ctx, env, fields)?),
+        TypeRecord(fields) => Value::RecordType(eval_fields(ctx, env, fields)?),
+        TypeEnum(alts) => {
+            let mut values = Vec::with_capacity(alts.len());
+            for (name, typ) in alts {
+                let typ = match typ {
+                    Some(typ) => Some(eval_expr(ctx, env, typ)?),
+                    None => None,
+                };
+                values.push((*name, typ));
             }
-            Term1(Term(Merge(merge_table, t))) => {
-                ctx = in_place_term(ctx, t.as_mut())?;
-                log::trace!(
-                    "{:4} eval Merge ( {:?} , {} )",
-                    line!(),
-                    merge_table,
-                    Show(t.as_ref())
-                );
-                for (_, data_handler) in merge_table.iter_mut() {
-                    ctx = data_handler.eval(ctx)?;
-                }
-                match t.as_mut() {
-                    Expr(e) => match e.as_mut() {
-                        Term1(Evaluation(eval_f, eval_a)) => match eval_f.as_mut() {
-                            Term(FieldAccess(fields_t, fields_n)) => {
-                                match fields_t.as_mut() {
-                                    TypeEnum(type_enum) => {
-                                        // This is in fact a type-enum-variant construction -- the
-                                        // only thing expected to evalluate as merge's argument.
-                                        //
-                                        //  < a : Natural >.a 12
-                                        //
-                                        // In typeless dust, we actually only care for the field
-                                        // name...
-                                        // But normalize non-the-less
-                                        for (_, arg_type) in type_enum.iter_mut() {
-                                            ctx = arg_type.eval(ctx)?;
-                                        }
-                                        // And look it up in the merge table
-                                        ctx = in_place_term(ctx, eval_a)?;
-                                        let mut mti = merge_table.iter_mut();
-                                        loop {
-                                            match mti.next() {
-                                                Some((name, data_handler)) => {
-                                                    // Only care for the first/only element:
-                                                    let name = name.front().unwrap();
-                                                    if fields_n == name {
-                                                        // Call the merge handler with the variant data.
-                                                        match data_handler.as_mut() {
-                                                            Lambda(_, _, _) => {
-                                                                let data_handler = ctx.rebox(Term(Expr(mem::take(data_handler))));
-                                                                let mut re_eval = Term1(Evaluation(data_handler, mem::take(eval_a)));
-                                                                log::trace!("{:4} Merge result re-evaluation: {:?}", line!(), re_eval);
-                                                                ctx = re_eval.eval(ctx)?;
-                                                                break Err(Some(re_eval))
-                                                            }
-                                                            o => panic!("Expecting Lambda for merge handler data: {:?}", o),
-                                                        }
-                                                    }
-                                                }
-                                                None => {
-                                                    bail!(
-                                                        "Field not found: {} in {:?}",
-                                                        fields_n,
-                                                        type_enum
-                                                    );
-                                                }
-                                            }
-                                        }
-                                    }
-                                    t if ctx.is_thunk_term(t)? => Ok(None),
-                                    o => {
-                                        let o = format!("{:?}", o);
-                                        let end = o.char_indices().take(30).map(|(i, _)| i).last().unwrap_or(0);
-                                        panic!("Merge expression has to evaluate to an enum-type construction: Evaluation(FiledAccess(TypeEnum(...), ...), ...): {}", &o[0..end]);
-                                    }
-                                }
-                            }
-                            t if ctx.is_thunk_term1(&*t)? => Ok(None),
-                            o => panic!("Merge expression has to evaluate to an enum-type construction: Evaluation(FieldAccess, ...): {:?}", o),
-                        },
-                        o => panic!("Merge expression has to evaluate to an enum-type construction: {:?}", o),
-                    },
-                    t if ctx.is_thunk_term(t)? => Ok(None),
-                    o => panic!("Merge term can be an expression or thunk term: {:?}", o),
-                }
+            Value::Union(values)
+        }
+        Import(import) if import.target == ast::ImportTarget::Missing => return Err(Error::import("missing")),
+        Import { .. } | Embed(_) => Value::Syntax(t),
+        Expr(e) => return eval_expr(ctx, env, e),
+        Merge(handlers, t) => {
+            let handlers = eval_fields(ctx, env, handlers)?;
+            return merge(ctx, handlers, eval_term(ctx, env, t)?);
+        }
+        ToMap(t) => return to_map(ctx, eval_term(ctx, env, t)?),
+        ShowConstructor(t) => {
+            let t = eval_term(ctx, env, t)?;
+            match alternative(&t) {
+                Some((name, _)) => Value::Text(vec![(Cow::Borrowed(unquote(name)), None)]),
+                None if is_neutral(&t) => Value::ShowConstructor(t),
+                None => return Err(EvalError::NotAUnion(show_value(ctx, &t)).into()),
             }
-            Term1(Arrow(n, a, b)) => {
-                log::trace!(
-                    "{:4} eval Arrow {:?} {} {}",
-                    line!(),
-                    n,
-                    Show(a.as_ref()),
-                    Show(b.as_ref())
-                );
-                ctx = a.eval(ctx)?;
-
-                ctx.sym_table.enter_scope();
-                if let Some(name) = n {
-                    ctx.sym_table.add_thunk(name);
-                }
-                ctx = b.eval(ctx)?;
-                ctx.sym_table.exit_scope();
+        }
+        Path(_) => return Err(EvalError::Unsupported(show(t)).into()),
+    }))
+}
+
+fn eval_fields<'a, 'i>(
+    ctx: &Context,
+    env: &Env<'a, 'i>,
+    fields: &'a ast::RecordData<'i>,
+) -> Result<Fields<'a, 'i>> {
+    let mut values = Vec::with_capacity(fields.len());
+    for (path, val) in fields {
+        if let Some(name) = path.front() {
+            values.push((*name, eval_expr(ctx, env, val)?));
+        }
+    }
+    Ok(values)
+}
+
+/// Apply `f` to `x`: run a closure, or reduce a saturated builtin.
+pub fn apply<'a, 'i>(ctx: &Context, f: V<'a, 'i>, x: V<'a, 'i>) -> Result<V<'a, 'i>> {
+    match &*f {
+        Value::Lam(_, _, closure) => closure.apply(ctx, x),
+        Value::Field(u, _) if matches!(**u, Value::Union(_)) => Ok(Rc::new(Value::App(f, x))),
+        f_ if is_neutral(f_) => {
+            let app = Rc::new(Value::App(f, x));
+            Ok(builtins::reduce(ctx, &app)?.unwrap_or(app))
+        }
+        _ => Err(EvalError::NotAFunction(show_value(ctx, &f)).into()),
+    }
+}
+
+/// Whether `v` is stuck on something unknown, and so may turn out to be of
+/// any shape.
+fn is_neutral(v: &Value) -> bool {
+    use Value::*;
+
+    match v {
+        Var(_, _) | Builtin(_) | Operation(_, _, _) | If(_, _, _) | Project(_, _) | ProjectBy(_, _)
+        | Merge(_, _, _) | ToMap(_, _) | ShowConstructor(_) | With(_, _, _) | Syntax(_) => true,
+        Field(u, _) => !matches!(**u, Union(_)),
+        App(_, _) => alternative(v).is_none(),
+        _ => false,
+    }
+}
+
+/// Whether `v` is stuck on something unknown, and may still turn out to be a
+/// record. Builtin constants such as `True` never do.
+fn is_unknown(v: &Value) -> bool {
+    is_neutral(v) && !matches!(v, Value::Builtin(_))
+}
+
+fn field<'a, 'i>(ctx: &Context, t: V<'a, 'i>, name: Ident<'i>) -> Result<V<'a, 'i>> {
+    match &*t {
+        Value::Record(fields) => match fields.iter().find(|(n, _)| *n == name) {
+            Some((_, value)) => Ok(value.clone()),
+            None => Err(EvalError::MissingField(name.to_string(), show_value(ctx, &t)).into()),
+        },
+        // A union constructor
+        Value::Union(alts) if alts.iter().any(|(n, _)| *n == name) => Ok(Rc::new(Value::Field(t, name))),
+        Value::Union(_) => Err(EvalError::MissingField(name.to_string(), show_value(ctx, &t)).into()),
+        Value::Project(r, _) => field(ctx, r.clone(), name),
+        t_ if is_neutral(t_) => Ok(Rc::new(Value::Field(t, name))),
+        _ => Err(EvalError::NotARecord(show_value(ctx, &t)).into()),
+    }
+}
+
+fn project<'a, 'i>(ctx: &Context, t: V<'a, 'i>, names: Vec<Ident<'i>>) -> Result<V<'a, 'i>> {
+    match &*t {
+        Value::Record(fields) => {
+            let fields = fields.iter().filter(|(n, _)| names.contains(n)).cloned().collect();
+            Ok(Rc::new(Value::Record(fields)))
+        }
+        _ if names.is_empty() => Ok(Rc::new(Value::Record(vec![]))),
+        t_ if is_neutral(t_) => Ok(Rc::new(Value::Project(t, names))),
+        _ => Err(EvalError::NotARecord(show_value(ctx, &t)).into()),
+    }
+}
+
+/// The alternative a union (or Optional) value was built with, and its
+/// argument.
+fn alternative<'v, 'a, 'i>(t: &'v Value<'a, 'i>) -> Option<(Ident<'i>, Option<&'v V<'a, 'i>>)> {
+    use Value::*;
+
+    match t {
+        Field(u, name) if matches!(**u, Union(_)) => Some((name, None)),
+        App(f, arg) => match &**f {
+            Field(u, name) if matches!(**u, Union(_)) => Some((name, Some(arg))),
+            Builtin("Some") => Some(("Some", Some(arg))),
+            Builtin("None") => Some(("None", None)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn merge<'a, 'i>(ctx: &Context, handlers: Fields<'a, 'i>, t: V<'a, 'i>) -> Result<V<'a, 'i>> {
+    let (name, arg) = match alternative(&t) {
+        Some(alt) => alt,
+        None if is_neutral(&t) => return Ok(Rc::new(Value::Merge(handlers, t, None))),
+        None => return Err(EvalError::InvalidMergeArgument(show_value(ctx, &t)).into()),
+    };
+    let handler = match handlers.iter().find(|(n, _)| *n == name) {
+        Some((_, handler)) => handler.clone(),
+        None => {
+            let handlers = show_value(ctx, &Value::Record(handlers));
+            return Err(EvalError::MissingField(name.to_string(), handlers).into());
+        }
+    };
+    match arg {
+        // Call the handler with the data of the alternative.
+        Some(arg) => apply(ctx, handler, arg.clone()),
+        None => Ok(handler),
+    }
+}
+
+fn to_map<'a, 'i>(ctx: &Context, t: V<'a, 'i>) -> Result<V<'a, 'i>> {
+    match &*t {
+        Value::Record(fields) => {
+            let mut fields = fields.clone();
+            fields.sort_by_key(|(name, _)| unquote(name));
+            let entries = fields
+                .into_iter()
+                .map(|(name, value)| {
+                    let key = Rc::new(Value::Text(vec![(Cow::Borrowed(unquote(name)), None)]));
+                    Rc::new(Value::Record(vec![("mapKey", key), ("mapValue", value)]))
+                })
+                .collect();
+            Ok(Rc::new(Value::List(entries)))
+        }
+        t_ if is_neutral(t_) => Ok(Rc::new(Value::ToMap(t, None))),
+        _ => Err(EvalError::NotARecord(show_value(ctx, &t)).into()),
+    }
+}
+
+/// A label without its backquotes.
+pub fn unquote(label: &str) -> &str {
+    label
+        .strip_prefix('`')
+        .and_then(|l| l.strip_suffix('`'))
+        .unwrap_or(label)
+}
 
-                Ok(None)
+/// `t with path = val`, or the update itself while `t` is not known enough.
+fn with<'a, 'i>(ctx: &Context, t: V<'a, 'i>, path: &[Ident<'i>], val: V<'a, 'i>) -> Result<V<'a, 'i>> {
+    let (name, rest) = (path[0], &path[1..]);
+    let update = |ctx: &Context, old: V<'a, 'i>| match rest {
+        [] => Ok(val.clone()),
+        rest => with(ctx, old, rest, val.clone()),
+    };
+    match &*t {
+        Value::Record(fields) if name != "?" => {
+            let mut fields = fields.clone();
+            match fields.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, old)) => *old = update(ctx, old.clone())?,
+                None => fields.push((name, update(ctx, Rc::new(Value::Record(vec![])))?)),
             }
-            Term1(IfThenElse(c, a, b)) => {
-                ctx = a.eval(ctx)?;
-                ctx = b.eval(ctx)?;
-                ctx = c.eval(ctx)?;
-                match c.as_ref() {
-                    Term1(Term(Var("True", 0))) => Err(Some(ctx.unbox(a))),
-                    Term1(Term(Var("False", 0))) => Err(Some(ctx.unbox(b))),
-                    Term1(t1) if ctx.is_thunk_term1(t1)? => Ok(None),
-                    other => panic!("How to eval if-then-else? {:?}", other),
+            Ok(Rc::new(Value::Record(fields)))
+        }
+        Value::App(f, x) if name == "?" => match &**f {
+            Value::Builtin("Some") => Ok(Rc::new(Value::App(f.clone(), update(ctx, x.clone())?))),
+            Value::Builtin("None") => Ok(t.clone()),
+            _ => Ok(Rc::new(Value::With(t.clone(), path.to_vec(), val))),
+        },
+        _ => Ok(Rc::new(Value::With(t, path.to_vec(), val))),
+    }
+}
+
+pub fn operation<'a, 'i>(ctx: &Context, op: &'i str, a: V<'a, 'i>, b: V<'a, 'i>) -> Result<V<'a, 'i>> {
+    use Value::*;
+
+    let value = match (op, &*a, &*b) {
+        ("⫽" | "//", Record(x), Record(y)) => {
+            let mut fields = x.clone();
+            for (name, value) in y {
+                match fields.iter_mut().find(|(n, _)| n == name) {
+                    Some((_, old)) => *old = value.clone(),
+                    None => fields.push((name, value.clone())),
                 }
             }
-            Lambda(name, a, b) => {
-                log::trace!(
-                    "{:4} eval Lambda {} : {} → {}",
-                    line!(),
-                    name,
-                    Show(a.as_ref().map(|a| a.as_ref()).unwrap_or(&<_>::default())),
-                    Show(b.as_ref())
-                );
-                ctx = a.eval(ctx)?;
-
-                ctx.sym_table.enter_scope();
-                ctx.sym_table.add_thunk(name);
-                ctx = b.eval(ctx)?;
-                ctx.sym_table.exit_scope();
-
-                log::trace!(
-                    "{:4} eval Lambda => {} → {}",
-                    line!(),
-                    name,
-                    Show(b.as_ref())
-                );
-                Ok(None)
+            Record(fields)
+        }
+        ("∧" | "/\\", Record(x), Record(y)) => Record(combine(ctx, x, y, op)?),
+        ("⩓" | "//\\\\", RecordType(x), RecordType(y)) => RecordType(combine(ctx, x, y, op)?),
+        ("≡" | "===", _, _) => Operation(op, a, b),
+        ("#", List(x), List(y)) => List(x.iter().chain(y).cloned().collect()),
+        ("#", List(x), EmptyList(_)) | ("#", EmptyList(_), List(x)) if !x.is_empty() => List(x.clone()),
+        ("*", Natural(x), Natural(y)) => Natural(x * y),
+        ("+", Natural(x), Natural(y)) => Natural(x + y),
+        ("++", Text(x), Text(y)) => return Ok(text(x.iter().chain(y).cloned().collect())),
+        _ => match simplify(ctx, op, &a, &b) {
+            Some(Simplified::Left) => return Ok(a),
+            Some(Simplified::Right) => return Ok(b),
+            Some(Simplified::Bool(value)) => Builtin(if value { "True" } else { "False" }),
+            None if is_neutral(&a) || is_neutral(&b) => Operation(op, a, b),
+            None => {
+                let (a, b) = (show_value(ctx, &a), show_value(ctx, &b));
+                return Err(EvalError::InvalidOperation(op.to_string(), a, b).into());
             }
-            other => {
-                let code = format!("{}", Show(other.as_imm()));
-                let end = code
-                    .char_indices()
-                    .take(20)
-                    .last()
-                    .map(|(i, _)| i)
-                    .unwrap_or(code.len());
-                panic!("How to eval {}\n{:?}", &code[..end], other)
+        },
+    };
+    Ok(Rc::new(value))
+}
+
+/// Recursively merge the fields of `b` into `a`, as `∧` does for records and
+/// `⩓` for record types.
+fn combine<'a, 'i>(ctx: &Context, a: &Fields<'a, 'i>, b: &Fields<'a, 'i>, op: &'i str) -> Result<Fields<'a, 'i>> {
+    let mut fields = a.clone();
+    for (name, value) in b {
+        let old = match fields.iter_mut().find(|(n, _)| n == name) {
+            Some((_, old)) => old,
+            None => {
+                fields.push((name, value.clone()));
+                continue;
             }
         };
-
-        match norm {
-            Ok(Some(n)) => mem::swap(n, self),
-            Err(Some(n)) => *self = n,
-            _ => mem::swap(self, value),
+        *old = match (&**old, &**value) {
+            (Value::Record(_), Value::Record(_)) | (Value::RecordType(_), Value::RecordType(_)) => {
+                operation(ctx, op, old.clone(), value.clone())?
+            }
+            // Left for when the operands are known.
+            (x, y) if is_unknown(x) || is_unknown(y) => operation(ctx, op, old.clone(), value.clone())?,
+            _ => return Err(EvalError::FieldCollision(name.to_string()).into()),
         };
-        Ok(ctx)
     }
+    Ok(fields)
 }
 
-/// The context of an evaluation.
-pub struct Context<'i> {
-    sym_table: SymTable<'i>,
-    _shelf_box_expr: Vec<Box<ast::Expr<'i>>>,
-    _shelf_box_term1: Vec<Box<ast::Term1<'i>>>,
-    _shelf_box_term: Vec<Box<ast::Term<'i>>>,
+/// What an operation reduces to without looking into its operands.
+enum Simplified {
+    Left,
+    Right,
+    Bool(bool),
 }
 
-impl<'i> Context<'i> {
-    /// Create an empty evaluation context.
-    pub fn new() -> Self {
-        let mut ctx = Self {
-            sym_table: <_>::default(),
-            _shelf_box_expr: <_>::default(),
-            _shelf_box_term1: <_>::default(),
-            _shelf_box_term: <_>::default(),
-        };
-        ctx.init();
-        ctx
-    }
-
-    fn init(&mut self) {
-        let Self { sym_table, .. } = self;
-        sym_table.enter_scope();
-
-        {
-            let mut def_thunk = |a| sym_table.add(a, None, None);
-
-            // Install core
-            def_thunk("assert");
-            def_thunk("Bool");
-            def_thunk("Double");
-            def_thunk("Double/show");
-            def_thunk("False");
-            def_thunk("Integer");
-            def_thunk("Integer/clamp");
-            def_thunk("Integer/negate");
-            def_thunk("Integer/show");
-            def_thunk("Integer/toDouble");
-            def_thunk("Kind");
-            def_thunk("List");
-            def_thunk("List/build");
-            def_thunk("List/fold");
-            def_thunk("List/head");
-            def_thunk("List/indexed");
-            def_thunk("List/last");
-            def_thunk("List/length");
-            def_thunk("List/reverse");
-            def_thunk("Natural");
-            def_thunk("Natural/even");
-            def_thunk("Natural/build");
-            def_thunk("Natural/fold");
-            def_thunk("Natural/isZero");
-            def_thunk("Natural/odd");
-            def_thunk("Natural/show");
-            def_thunk("Natural/subtract");
-            def_thunk("Natural/toInteger");
-            def_thunk("None");
-            def_thunk("Optional");
-            def_thunk("Some");
-            def_thunk("Text");
-            def_thunk("Text/replace");
-            def_thunk("Text/show");
-            def_thunk("toMap");
-            def_thunk("True");
-            def_thunk("Type");
-        }
-    }
-
-    fn unbox<T>(&mut self, val: &mut Box<T>) -> T
-    where
-        T: Default,
-        Self: Unboxer<T>,
-    {
-        let mut boxval = mem::take(val);
-        let val = mem::take(boxval.as_mut());
-        self.box_shelf().push(boxval);
-        val
+/// The Bool semantics and the identities of the operators.
+fn simplify<'a, 'i>(ctx: &Context, op: &str, a: &Value<'a, 'i>, b: &Value<'a, 'i>) -> Option<Simplified> {
+    use Simplified::*;
+
+    let boolean = |v: &Value, value: &str| matches!(v, Value::Builtin(n) if *n == value);
+    let natural = |v: &Value, value: u8| matches!(v, Value::Natural(n) if *n == value.into());
+    let empty_text = |v: &Value| matches!(v, Value::Text(chunks) if chunks.is_empty());
+    let empty_list = |v: &Value| match v {
+        Value::List(items) => items.is_empty(),
+        Value::EmptyList(_) => true,
+        _ => false,
+    };
+    let empty_record = |v: &Value| matches!(v, Value::Record(fields) if fields.is_empty());
+    let empty_record_type = |v: &Value| matches!(v, Value::RecordType(fields) if fields.is_empty());
+
+    match op {
+        "&&" if boolean(a, "True") || boolean(b, "False") => Some(Right),
+        "&&" if boolean(a, "False") || boolean(b, "True") => Some(Left),
+        "||" if boolean(a, "False") || boolean(b, "True") => Some(Right),
+        "||" if boolean(a, "True") || boolean(b, "False") => Some(Left),
+        "&&" | "||" if conv(ctx, a, b) => Some(Left),
+        "==" if boolean(a, "True") => Some(Right),
+        "==" if boolean(b, "True") => Some(Left),
+        "==" if conv(ctx, a, b) => Some(Bool(true)),
+        "!=" if boolean(a, "False") => Some(Right),
+        "!=" if boolean(b, "False") => Some(Left),
+        "!=" if conv(ctx, a, b) => Some(Bool(false)),
+        "+" if natural(a, 0) => Some(Right),
+        "+" if natural(b, 0) => Some(Left),
+        "*" if natural(a, 0) || natural(b, 1) => Some(Left),
+        "*" if natural(b, 0) || natural(a, 1) => Some(Right),
+        "++" if empty_text(a) => Some(Right),
+        "++" if empty_text(b) => Some(Left),
+        "#" if empty_list(a) => Some(Right),
+        "#" if empty_list(b) => Some(Left),
+        "∧" | "/\\" | "⫽" | "//" if empty_record(a) => Some(Right),
+        "∧" | "/\\" | "⫽" | "//" if empty_record(b) => Some(Left),
+        "⫽" | "//" if conv(ctx, a, b) => Some(Left),
+        "⩓" | "//\\\\" if empty_record_type(a) => Some(Right),
+        "⩓" | "//\\\\" if empty_record_type(b) => Some(Left),
+        _ => None,
     }
+}
 
-    fn rebox<T>(&mut self, mut val: T) -> Box<T>
-    where
-        Self: Unboxer<T>,
-    {
-        match self.box_shelf().pop() {
-            Some(mut b) => {
-                mem::swap(&mut val, b.as_mut());
-                b
+/// A text literal from its chunks: interpolated text literals are spliced in
+/// and adjacent chunks merged. `"${t}"` is `t`.
+pub fn text<'a, 'i>(chunks: Vec<Chunk<'a, 'i>>) -> V<'a, 'i> {
+    fn splice<'a, 'i>(flat: &mut Vec<Chunk<'a, 'i>>, chunk: &mut Cow<'i, str>, chunks: Vec<Chunk<'a, 'i>>) {
+        for (c, hole) in chunks {
+            if chunk.is_empty() {
+                *chunk = c;
+            } else {
+                chunk.to_mut().push_str(&c);
+            }
+            match hole {
+                None => (),
+                Some(hole) => match &*hole {
+                    Value::Text(inner) => splice(flat, chunk, inner.clone()),
+                    _ => flat.push((mem::take(chunk), Some(hole))),
+                },
             }
-            None => Box::new(val),
         }
     }
 
-    fn is_thunk_term(&self, t: &ast::Term) -> Result<bool> {
-        use ast::Term::*;
+    let mut flat = Vec::new();
+    let mut chunk = Cow::Borrowed("");
+    splice(&mut flat, &mut chunk, chunks);
+    if !chunk.is_empty() {
+        flat.push((chunk, None));
+    }
 
-        Ok(match t {
-            Integer(_) | Record(_) | List(_) | Text(_, _) | Double(_) => false,
-            Var(n, s) => self.sym_table.is_thunk1(n, *s)?,
-            FieldAccess(t, _) => self.is_thunk_term(t)?,
-            Project(1, t, _) => self.is_thunk_term(t)?,
-            Merge(_, t) => self.is_thunk_term(t)?,
-            Expr(e) => self.is_thunk_expr(e)?,
-            TypeEnum(_) => true,
-            other => panic!("How to know if thunk term? {:?} ", other,),
-        })
+    match flat.as_slice() {
+        [(c, Some(hole))] if c.is_empty() => hole.clone(),
+        _ => Rc::new(Value::Text(flat)),
     }
+}
 
-    fn is_thunk_term1(&self, t: &ast::Term1) -> Result<bool> {
-        use ast::Term1::*;
+/// Whether `a` and `b` are equivalent: equal up to the names of bound
+/// variables.
+pub fn conv<'a, 'i>(ctx: &Context, a: &Value<'a, 'i>, b: &Value<'a, 'i>) -> bool {
+    use Value::*;
 
-        Ok(match t {
-            Term(t) => self.is_thunk_term(t)?,
-            Evaluation(t, _) => self.is_thunk_term1(t)?,
-            Operation(a, _, b) => self.is_thunk_term1(a)? || self.is_thunk_term1(b)?,
-            IfThenElse(c, _, _) => self.is_thunk_expr(c)?,
-            Ascribe(a, _) => self.is_thunk_term1(a.as_ref())?,
-            other => panic!("How to know if thunk term1? {:?}", other,),
-        })
+    let all = |a: &[V<'a, 'i>], b: &[V<'a, 'i>]| a.len() == b.len() && a.iter().zip(b).all(|(a, b)| conv(ctx, a, b));
+    let opt = |a: &Option<V<'a, 'i>>, b: &Option<V<'a, 'i>>| match (a, b) {
+        (Some(a), Some(b)) => conv(ctx, a, b),
+        (a, b) => a.is_none() && b.is_none(),
+    };
+    let fields = |a: &Fields<'a, 'i>, b: &Fields<'a, 'i>| {
+        a.len() == b.len()
+            && a.iter().all(|(n, x)| b.iter().any(|(m, y)| unquote(n) == unquote(m) && conv(ctx, x, y)))
+    };
+    let under = |x: &Closure<'a, 'i>, y: &Closure<'a, 'i>, name: Ident<'i>| {
+        let var = ctx.fresh(name);
+        match (x.apply(ctx, var.clone()), y.apply(ctx, var)) {
+            (Ok(x), Ok(y)) => conv(ctx, &x, &y),
+            _ => false,
+        }
+    };
+
+    match (a, b) {
+        (Lam(n, ta, x), Lam(_, tb, y)) => opt(ta, tb) && under(x, y, *n),
+        (Pi(n, ta, x), Pi(_, tb, y)) => conv(ctx, ta, tb) && under(x, y, n.unwrap_or("_")),
+        (Var(_, i), Var(_, j)) => i == j,
+        (Builtin(x), Builtin(y)) => unquote(x) == unquote(y),
+        (Natural(x), Natural(y)) => x == y,
+        (Integer(x), Integer(y)) => x == y,
+        (Double(x), Double(y)) => x.to_bits() == y.to_bits(),
+        (Bytes(x), Bytes(y)) => x == y,
+        (Text(x), Text(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|((c, h), (d, k))| c == d && opt(h, k))
+        }
+        (List(x), List(y)) => all(x, y),
+        (EmptyList(x), EmptyList(y)) => conv(ctx, x, y),
+        (Record(x), Record(y)) | (RecordType(x), RecordType(y)) => fields(x, y),
+        (Union(x), Union(y)) => {
+            x.len() == y.len()
+                && x.iter().all(|(n, t)| y.iter().any(|(m, u)| unquote(n) == unquote(m) && opt(t, u)))
+        }
+        (App(f, x), App(g, y)) => conv(ctx, f, g) && conv(ctx, x, y),
+        (Operation(o, a, b), Operation(p, c, d)) => {
+            canonical(o) == canonical(p) && conv(ctx, a, c) && conv(ctx, b, d)
+        }
+        (If(a, b, c), If(d, e, f)) => conv(ctx, a, d) && conv(ctx, b, e) && conv(ctx, c, f),
+        (Field(x, n), Field(y, m)) => n == m && conv(ctx, x, y),
+        (Project(x, n), Project(y, m)) => n == m && conv(ctx, x, y),
+        (ProjectBy(x, s), ProjectBy(y, t)) => conv(ctx, x, y) && conv(ctx, s, t),
+        (Merge(h, x, s), Merge(k, y, t)) => fields(h, k) && conv(ctx, x, y) && opt(s, t),
+        (ToMap(x, s), ToMap(y, t)) => conv(ctx, x, y) && opt(s, t),
+        (ShowConstructor(x), ShowConstructor(y)) | (Assert(x), Assert(y)) => conv(ctx, x, y),
+        (With(x, p, u), With(y, q, v)) => p == q && conv(ctx, x, y) && conv(ctx, u, v),
+        (Syntax(x), Syntax(y)) => show(*x) == show(*y),
+        _ => false,
     }
+}
 
-    fn is_thunk_expr(&self, t: &ast::Expr) -> Result<bool> {
-        use ast::Expr::*;
+/// The unicode spelling of an operator.
+fn canonical(op: &str) -> &str {
+    match op {
+        "/\\" => "∧",
+        "//" => "⫽",
+        "//\\\\" => "⩓",
+        "===" => "≡",
+        op => op,
+    }
+}
 
-        Ok(match t {
-            Term1(t1) => self.is_thunk_term1(t1)?,
-            Lambda(_, _, _) => true,
-            other => panic!("How to know if thunk expr? {:?}", other,),
-        })
+/// Read a value back into syntax. `names` are the binders looked under, with
+/// the ids of their variables.
+pub fn quote<'a, 'i>(ctx: &Context, names: &mut Vec<(Ident<'i>, usize)>, v: &Value<'a, 'i>) -> Result<ast::Expr<'i>> {
+    use ast::{Term1, Term as T};
+
+    let boxed = |names: &mut Vec<_>, v: &Value<'a, 'i>| quote(ctx, names, v).map(Box::new);
+
+    Ok(match v {
+        Value::Lam(name, typ, closure) => {
+            let typ = match typ {
+                Some(typ) => Some(boxed(names, typ)?),
+                None => None,
+            };
+            let body = quote_under(ctx, names, name, closure)?;
+            ast::Expr::Lambda(name, typ, body)
+        }
+        Value::Pi(name, typ, closure) => {
+            let typ = boxed(names, typ)?;
+            let body = quote_under(ctx, names, name.unwrap_or("_"), closure)?;
+            Term1::Arrow(*name, typ, body).into()
+        }
+        Value::Var(name, id) => {
+            // Out of context (for error messages), variables are left as named.
+            let idx = match names.iter().rposition(|(_, i)| i == id) {
+                Some(pos) => names[pos + 1..].iter().filter(|(n, _)| n == name).count(),
+                None => 0,
+            };
+            T::Var(name, idx as u16).into()
+        }
+        Value::Builtin(name) => {
+            let idx = names.iter().filter(|(n, _)| n == name).count();
+            T::Var(name, idx as u16).into()
+        }
+        Value::Natural(n) => T::Natural(n.clone()).into(),
+        Value::Integer(n) => T::Integer(n.clone()).into(),
+        Value::Double(d) => T::Double(*d).into(),
+        Value::Bytes(bytes) => T::Bytes(bytes.clone()).into(),
+        Value::Text(chunks) => {
+            let mut entries = ast::Deq::with_capacity(chunks.len());
+            for (chunk, hole) in chunks {
+                let hole = match hole {
+                    Some(hole) => Some(boxed(names, hole)?),
+                    None => None,
+                };
+                entries.push_back((chunk.clone(), hole));
+            }
+            T::Text(1, entries).into()
+        }
+        Value::List(items) => {
+            T::List(items.iter().map(|v| boxed(names, v)).collect::<Result<_>>()?).into()
+        }
+        Value::EmptyList(typ) => {
+            let list = Box::new(Term1::Term(T::List(<_>::default())));
+            Term1::Ascribe(list, boxed(names, typ)?).into()
+        }
+        Value::Record(fields) => T::Record(quote_fields(ctx, names, fields)?).into(),
+        Value::RecordType(fields) => T::TypeRecord(quote_fields(ctx, names, fields)?).into(),
+        Value::Union(alts) => {
+            let mut alts: Vec<_> = alts.iter().collect();
+            alts.sort_by_key(|(name, _)| unquote(name));
+            let mut data = ast::TypeEnumData::with_capacity(alts.len());
+            for (name, typ) in alts {
+                let typ = match typ {
+                    Some(typ) => Some(boxed(names, typ)?),
+                    None => None,
+                };
+                data.push_back((*name, typ));
+            }
+            T::TypeEnum(data).into()
+        }
+        Value::App(f, x) => {
+            let f = head(quote(ctx, names, f)?);
+            Term1::Evaluation(Box::new(f), term(quote(ctx, names, x)?)).into()
+        }
+        Value::Operation(op, a, b) => {
+            let a = term1(quote(ctx, names, a)?);
+            let b = term1(quote(ctx, names, b)?);
+            Term1::Operation(Box::new(a), op, Box::new(b)).into()
+        }
+        Value::If(c, a, b) => Term1::IfThenElse(boxed(names, c)?, boxed(names, a)?, boxed(names, b)?).into(),
+        Value::Field(t, name) => T::FieldAccess(Box::new(term(quote(ctx, names, t)?)), name).into(),
+        Value::Project(t, labels) => {
+            let labels = labels.iter().map(|n| Term1::Term(T::Var(n, 0))).collect();
+            T::Project(1, Box::new(term(quote(ctx, names, t)?)), labels).into()
+        }
+        Value::ProjectBy(t, s) => {
+            let s = ast::deq(term1(quote(ctx, names, s)?));
+            T::Project(2, Box::new(term(quote(ctx, names, t)?)), s).into()
+        }
+        Value::Merge(handlers, t, typ) => {
+            let merge = T::Merge(quote_fields(ctx, names, handlers)?, Box::new(term(quote(ctx, names, t)?)));
+            annotate(ctx, names, merge, typ)?
+        }
+        Value::ToMap(t, typ) => {
+            let to_map = T::ToMap(Box::new(term(quote(ctx, names, t)?)));
+            annotate(ctx, names, to_map, typ)?
+        }
+        Value::ShowConstructor(t) => T::ShowConstructor(Box::new(term(quote(ctx, names, t)?))).into(),
+        Value::With(t, path, val) => {
+            let t = head(quote(ctx, names, t)?);
+            let val = head(quote(ctx, names, val)?);
+            Term1::With(Box::new(t), path.iter().copied().collect(), Box::new(val)).into()
+        }
+        Value::Assert(typ) => Term1::Assert(boxed(names, typ)?).into(),
+        Value::Syntax(t) => (*t).clone().into(),
+    })
+}
+
+/// Quote the body of `closure`, applied to a new variable bound to `name`.
+fn quote_under<'a, 'i>(
+    ctx: &Context,
+    names: &mut Vec<(Ident<'i>, usize)>,
+    name: Ident<'i>,
+    closure: &Closure<'a, 'i>,
+) -> Result<ast::Val<'i>> {
+    let var = ctx.fresh(name);
+    let id = match *var {
+        Value::Var(_, id) => id,
+        _ => unreachable!(),
+    };
+    let body = closure.apply(ctx, var)?;
+    names.push((name, id));
+    let body = quote(ctx, names, &body);
+    names.pop();
+    Ok(Box::new(body?))
+}
+
+fn quote_fields<'a, 'i>(
+    ctx: &Context,
+    names: &mut Vec<(Ident<'i>, usize)>,
+    fields: &Fields<'a, 'i>,
+) -> Result<ast::RecordData<'i>> {
+    let mut fields: Vec<_> = fields.iter().collect();
+    fields.sort_by_key(|(name, _)| unquote(name));
+    let mut data = ast::RecordData::with_capacity(fields.len());
+    for (name, value) in fields {
+        data.push_back((ast::path([*name]), Box::new(quote(ctx, names, value)?)));
     }
+    Ok(data)
+}
+
+fn annotate<'a, 'i>(
+    ctx: &Context,
+    names: &mut Vec<(Ident<'i>, usize)>,
+    t: ast::Term<'i>,
+    typ: &Option<V<'a, 'i>>,
+) -> Result<ast::Expr<'i>> {
+    Ok(match typ {
+        Some(typ) => ast::Term1::Ascribe(Box::new(ast::Term1::Term(t)), Box::new(quote(ctx, names, typ)?)).into(),
+        None => t.into(),
+    })
 }
 
-type R<'i> = Result<Ctx<'i>>;
+/// `e` as a term, in parentheses unless it is one.
+fn term(e: ast::Expr) -> ast::Term {
+    match e {
+        ast::Expr::Term1(ast::Term1::Term(t)) => t,
+        e => ast::Term::Expr(Box::new(e)),
+    }
+}
+
+/// `e` as an operand, in parentheses unless it already is a `Term1`.
+fn term1(e: ast::Expr) -> ast::Term1 {
+    match e {
+        ast::Expr::Term1(t1) => t1,
+        e => ast::Term1::Term(ast::Term::Expr(Box::new(e))),
+    }
+}
+
+/// `e` as the head of an application, in parentheses unless it is a term
+/// or an application.
+fn head(e: ast::Expr) -> ast::Term1 {
+    match e {
+        ast::Expr::Term1(t1 @ (ast::Term1::Term(_) | ast::Term1::Evaluation(_, _))) => t1,
+        e => ast::Term1::Term(ast::Term::Expr(Box::new(e))),
+    }
+}
+
+fn show_value(ctx: &Context, v: &Value) -> String {
+    match quote(ctx, &mut Vec::new(), v) {
+        Ok(e) => show(&e),
+        Err(_) => "…".to_owned(),
+    }
+}
+
+fn show<'a, T>(t: &'a T) -> String
+where
+    Show<&'a T>: std::fmt::Display,
+{
+    format!("{}", Show(t))
+}
 
 impl<'i, T> Eval<'i> for &'i mut T
 where
     T: Eval<'i>,
 {
-    fn eval(&mut self, ctx: Ctx<'i>) -> R<'i> {
+    fn eval(&mut self, ctx: Ctx<'i>) -> Result<Ctx<'i>> {
         T::eval(*self, ctx)
     }
 }
@@ -654,7 +962,7 @@ impl<'i, T> Eval<'i> for Option<T>
 where
     T: Eval<'i>,
 {
-    fn eval(&mut self, ctx: Ctx<'i>) -> R<'i> {
+    fn eval(&mut self, ctx: Ctx<'i>) -> Result<Ctx<'i>> {
         match self {
             Some(t) => T::eval(t, ctx),
             _ => Ok(ctx),
@@ -666,29 +974,7 @@ impl<'i, T> Eval<'i> for Box<T>
 where
     T: Eval<'i>,
 {
-    fn eval(&mut self, ctx: Ctx<'i>) -> R<'i> {
+    fn eval(&mut self, ctx: Ctx<'i>) -> Result<Ctx<'i>> {
         T::eval(self.as_mut(), ctx)
     }
 }
-
-pub trait Unboxer<T> {
-    fn box_shelf(&mut self) -> &mut Vec<Box<T>>;
-}
-
-impl<'i> Unboxer<ast::Expr<'i>> for Context<'i> {
-    fn box_shelf(&mut self) -> &mut Vec<Box<ast::Expr<'i>>> {
-        &mut self._shelf_box_expr
-    }
-}
-
-impl<'i> Unboxer<ast::Term1<'i>> for Context<'i> {
-    fn box_shelf(&mut self) -> &mut Vec<Box<ast::Term1<'i>>> {
-        &mut self._shelf_box_term1
-    }
-}
-
-impl<'i> Unboxer<ast::Term<'i>> for Context<'i> {
-    fn box_shelf(&mut self) -> &mut Vec<Box<ast::Term<'i>>> {
-        &mut self._shelf_box_term
-    }
-}
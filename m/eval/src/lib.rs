@@ -1,18 +1,18 @@
 pub const VERSION: &str = "0.0.1";
 mod a_subst;
+mod builtins;
 mod eval;
-mod sym_table;
+mod typecheck;
 pub use {
-    a_subst::ASubstitution,
     error::{bail, Error, Result},
-    eval::{ctx, Context, Ctx, Eval},
+    eval::{ctx, equivalent, normalize, Context, Ctx, Eval},
     show::Show,
     std::collections::{
         hash_map::{Entry, HashMap as Map},
         hash_set::HashSet as Set,
         VecDeque as Deq,
     },
-    sym_table::{Info, SymTable, Value},
+    typecheck::{typecheck, TypeCheck, TypeCtx},
 };
 
 pub fn eval<'i, E>(ctx: Ctx<'i>, expr: &mut E) -> Result<Ctx<'i>>
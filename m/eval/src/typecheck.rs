@@ -0,0 +1,952 @@
+use super::{
+    a_subst::{beta, count, shift, VisitVars},
+    Error, Map, Result, Show,
+};
+use ast::{Expr, Ident, Location, Path, RecordData, Spans, Term, Term1, TypeEnumData};
+use error::TypeError;
+use std::{cell::RefCell, sync::OnceLock};
+
+/// The types of the builtins, as dhall source.
+const BUILTINS: &[(&str, &str)] = &[
+    ("Type", "Kind"),
+    ("Kind", "Sort"),
+    ("Bool", "Type"),
+    ("True", "Bool"),
+    ("False", "Bool"),
+    ("Natural", "Type"),
+    ("Integer", "Type"),
+    ("Double", "Type"),
+    ("Bytes", "Type"),
+    ("Text", "Type"),
+    ("List", "Type → Type"),
+    ("Optional", "Type → Type"),
+    ("None", "∀(A : Type) → Optional A"),
+    (
+        "Natural/build",
+        "(∀(natural : Type) → ∀(succ : natural → natural) → ∀(zero : natural) → natural) → Natural",
+    ),
+    (
+        "Natural/fold",
+        "Natural → ∀(natural : Type) → ∀(succ : natural → natural) → ∀(zero : natural) → natural",
+    ),
+    ("Natural/isZero", "Natural → Bool"),
+    ("Natural/even", "Natural → Bool"),
+    ("Natural/odd", "Natural → Bool"),
+    ("Natural/toInteger", "Natural → Integer"),
+    ("Natural/show", "Natural → Text"),
+    ("Natural/subtract", "Natural → Natural → Natural"),
+    ("Integer/toDouble", "Integer → Double"),
+    ("Integer/show", "Integer → Text"),
+    ("Integer/negate", "Integer → Integer"),
+    ("Integer/clamp", "Integer → Natural"),
+    ("Double/show", "Double → Text"),
+    (
+        "List/build",
+        "∀(a : Type) → (∀(list : Type) → ∀(cons : a → list → list) → ∀(nil : list) → list) → List a",
+    ),
+    (
+        "List/fold",
+        "∀(a : Type) → List a → ∀(list : Type) → ∀(cons : a → list → list) → ∀(nil : list) → list",
+    ),
+    ("List/length", "∀(a : Type) → List a → Natural"),
+    ("List/head", "∀(a : Type) → List a → Optional a"),
+    ("List/last", "∀(a : Type) → List a → Optional a"),
+    (
+        "List/indexed",
+        "∀(a : Type) → List a → List { index : Natural, value : a }",
+    ),
+    ("List/reverse", "∀(a : Type) → List a → List a"),
+    ("Text/show", "Text → Text"),
+    (
+        "Text/replace",
+        "∀(needle : Text) → ∀(replacement : Text) → ∀(haystack : Text) → Text",
+    ),
+];
+
+const UNIVERSES: [&str; 3] = ["Type", "Kind", "Sort"];
+
+/// The parsed types of the builtins, or why they failed to parse.
+fn builtin_types() -> Result<&'static Map<&'static str, Expr<'static>>> {
+    static TYPES: OnceLock<std::result::Result<Map<&'static str, Expr<'static>>, String>> = OnceLock::new();
+
+    let types = TYPES.get_or_init(|| {
+        BUILTINS
+            .iter()
+            .map(|&(name, typ)| match parse::parse_str(typ) {
+                Ok(typ) => Ok((name, typ)),
+                Err(e) => Err(format!("type of builtin {}: {}", name, Error::from(e))),
+            })
+            .collect()
+    });
+    types.as_ref().map_err(|e| Error::any(e.clone()))
+}
+
+/// Infer the type of a closed expression.
+pub fn typecheck<'i>(expr: &Expr<'i>) -> Result<Expr<'i>> {
+    expr.type_of(&mut TypeCtx::new())
+}
+
+pub trait TypeCheck<'i> {
+    /// Infer the (normalized) type of `self` in `ctx`.
+    fn type_of(&self, ctx: &mut TypeCtx<'i>) -> Result<Expr<'i>>;
+}
+
+struct Binding<'i> {
+    name: Ident<'i>,
+    typ: Expr<'i>,
+    value: Option<Expr<'i>>,
+    normal: RefCell<Option<Expr<'i>>>,
+}
+
+/// The typing context Γ.
+///
+/// Let-bound names keep their value, so that types depending on them can be
+/// normalized.
+pub struct TypeCtx<'i> {
+    gamma: Vec<Binding<'i>>,
+    spans: Option<&'i Spans<'i>>,
+}
+
+impl<'i> Default for TypeCtx<'i> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'i> TypeCtx<'i> {
+    /// Create an empty typing context.
+    pub fn new() -> Self {
+        Self {
+            gamma: <_>::default(),
+            spans: None,
+        }
+    }
+
+    /// Locate type errors using the spans the checked tree was parsed with.
+    pub fn with_spans(mut self, spans: &'i Spans<'i>) -> Self {
+        self.spans = Some(spans);
+        self
+    }
+
+    fn locate<T>(&self, node: &T) -> Option<Location> {
+        let spans = self.spans?;
+        spans.get(node).map(|span| spans.locate(span))
+    }
+
+    fn locate_str(&self, s: &str) -> Option<Location> {
+        let spans = self.spans?;
+        spans.of_str(s).map(|span| spans.locate(span))
+    }
+
+    fn push(&mut self, name: Ident<'i>, typ: Expr<'i>, value: Option<Expr<'i>>) {
+        log::trace!("{:4} Γ, {} : {}", line!(), name, Show(&typ));
+        self.gamma.push(Binding {
+            name,
+            typ,
+            value,
+            normal: <_>::default(),
+        });
+    }
+
+    fn pop(&mut self) -> Ident<'i> {
+        self.gamma.pop().unwrap().name
+    }
+
+    fn lookup(&self, name: &str, idx: u16) -> Result<Expr<'i>> {
+        let mut skip = idx;
+        for (k, b) in self.gamma.iter().enumerate().rev() {
+            if b.name != name {
+                continue;
+            }
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+            let mut typ = b.typ.clone();
+            for b in &self.gamma[k..] {
+                shift(&mut typ, 1, b.name, 0);
+            }
+            return Ok(typ);
+        }
+        match builtin_types()?.get(name) {
+            Some(typ) if skip == 0 => Ok(typ.clone()),
+            _ if name == "Sort" => Err(TypeError::Untyped(name.to_owned()).into()),
+            _ => Err(Error::from(TypeError::UnboundVariable(format!("{}@{}", name, idx)))
+                .at(self.locate_str(name))),
+        }
+    }
+
+    /// Normalize `expr` by eval, unfolding let-bound names of the context.
+    pub fn normalize(&self, expr: &Expr<'i>) -> Result<Expr<'i>> {
+        normalize_in(&self.gamma, expr)
+    }
+
+    /// Whether two (normalized) types are judgmentally equal in this context.
+    fn equivalent(&self, a: &Expr<'i>, b: &Expr<'i>) -> bool {
+        let names: Vec<_> = self.gamma.iter().map(|b| b.name).collect();
+        crate::equivalent(&names, a, b)
+    }
+
+    fn check_eq(&self, expected: &Expr<'i>, found: &Expr<'i>) -> Result<()> {
+        if self.equivalent(expected, found) {
+            Ok(())
+        } else {
+            Err(TypeError::Mismatch(show(expected), show(found)).into())
+        }
+    }
+
+    fn universe_of(&mut self, expr: &Expr<'i>) -> Result<usize> {
+        let typ = expr.type_of(self)?;
+        universe(&typ).ok_or_else(|| TypeError::NotAType(show(expr), show(&typ)).into())
+    }
+
+    fn check_type(&mut self, typ: &Expr<'i>) -> Result<()> {
+        match self.universe_of(typ)? {
+            0 => Ok(()),
+            _ => Err(TypeError::Mismatch("a term".to_owned(), show(typ)).into()),
+        }
+    }
+}
+
+impl<'i> TypeCheck<'i> for Expr<'i> {
+    fn type_of(&self, ctx: &mut TypeCtx<'i>) -> Result<Expr<'i>> {
+        use ast::Expr::*;
+        match self {
+            Term1(t1) => t1.type_of(ctx),
+            Let(defs, val) => {
+                for (name, ann, val) in defs {
+                    let typ = val.type_of(ctx)?;
+                    if let Some(ann) = ann {
+                        ctx.universe_of(ann)?;
+                        ctx.check_eq(&ctx.normalize(ann)?, &typ)?;
+                    }
+                    ctx.push(name, typ, Some(val.as_ref().to_owned()));
+                }
+
+                // Let-bound names are unfolded in normalized types, so there
+                // is nothing left to substitute.
+                let mut typ = val.type_of(ctx)?;
+                for _ in defs {
+                    let name = ctx.pop();
+                    shift(&mut typ, -1, name, 0);
+                }
+                Ok(typ)
+            }
+            Lambda(name, Some(arg_typ), val) => {
+                ctx.universe_of(arg_typ)?;
+                let arg_typ = ctx.normalize(arg_typ)?;
+
+                ctx.push(name, arg_typ.clone(), None);
+                let typ = val.type_of(ctx)?;
+                ctx.universe_of(&typ)?;
+                ctx.pop();
+
+                Ok(pi(Some(*name), arg_typ, typ))
+            }
+            Lambda(name, None, _) => Err(TypeError::UnannotatedLambda(name.to_string()).into()),
+        }
+    }
+}
+
+impl<'i> TypeCheck<'i> for Term1<'i> {
+    fn type_of(&self, ctx: &mut TypeCtx<'i>) -> Result<Expr<'i>> {
+        use ast::Term1::*;
+        match self {
+            Term(t) => t.type_of(ctx),
+            Evaluation(f, a) => match f.as_ref() {
+                Term(ast::Term::Var("Some", 0)) => {
+                    let typ = a.type_of(ctx)?;
+                    ctx.check_type(&typ)?;
+                    Ok(app(var("Optional"), typ))
+                }
+                f => match f.type_of(ctx)? {
+                    Expr::Term1(Arrow(name, arg_typ, mut typ)) => {
+                        let a_typ = a.type_of(ctx)?;
+                        ctx.check_eq(&arg_typ, &a_typ)?;
+                        beta(&mut typ, name.unwrap_or("_"), &a.to_owned().into());
+                        ctx.normalize(&typ)
+                    }
+                    typ => Err(TypeError::NotAFunction(show(f), show(&typ)).into()),
+                },
+            },
+            Arrow(name, arg_typ, typ) => {
+                let u1 = ctx.universe_of(arg_typ)?;
+                let arg_typ = ctx.normalize(arg_typ)?;
+
+                ctx.push(name.unwrap_or("_"), arg_typ, None);
+                let u2 = ctx.universe_of(typ)?;
+                ctx.pop();
+
+                // Functions into terms are terms, whatever their input.
+                Ok(universe_expr(if u2 == 0 { 0 } else { u1.max(u2) }))
+            }
+            With(t, path, val) => {
+                let typ = t.type_of(ctx)?;
+                let val_typ = val.type_of(ctx)?;
+                let path: Vec<_> = path.iter().copied().collect();
+                with_type(ctx, typ, &path, val_typ)
+            }
+            Operation(a, op, b) => {
+                let a_typ = a.type_of(ctx)?;
+                let b_typ = b.type_of(ctx)?;
+                type_of_operation(ctx, (a, &a_typ), op, (b, &b_typ))
+            }
+            IfThenElse(c, a, b) => {
+                let c_typ = c.type_of(ctx)?;
+                if !is_var(&c_typ, "Bool") {
+                    return Err(TypeError::InvalidPredicate(show(&c_typ)).into());
+                }
+                let a_typ = a.type_of(ctx)?;
+                ctx.check_type(&a_typ)?;
+                let b_typ = b.type_of(ctx)?;
+                ctx.check_eq(&a_typ, &b_typ)?;
+                Ok(a_typ)
+            }
+            Assert(typ) => {
+                ctx.check_type(typ)?;
+                let normal = ctx.normalize(typ)?;
+                let (a, b) = match &normal {
+                    Expr::Term1(Operation(a, "≡" | "===", b)) => {
+                        (Expr::Term1(a.as_ref().to_owned()), Expr::Term1(b.as_ref().to_owned()))
+                    }
+                    _ => return Err(TypeError::NotAnEquivalence(show(&normal)).into()),
+                };
+                if !ctx.equivalent(&a, &b) {
+                    let err = Error::from(TypeError::AssertionFailed(show(&a), show(&b)));
+                    return Err(err.at(ctx.locate(typ.as_ref())));
+                }
+                Ok(normal)
+            }
+            Ascribe(t, ann) => {
+                ctx.universe_of(ann)?;
+                let ann = ctx.normalize(ann)?;
+                let typ = match strip_term1(t) {
+                    Term(ast::Term::List(vals)) if vals.is_empty() => match list_element(&ann) {
+                        Some(elem) if ctx.universe_of(&elem)? == 0 => ann.clone(),
+                        _ => return Err(TypeError::InvalidListType(show(&ann)).into()),
+                    },
+                    Term(ast::Term::Merge(handlers, t)) => merge_type(ctx, handlers, t)?.unwrap_or_else(|| ann.clone()),
+                    Term(ast::Term::ToMap(r)) => match record_type(&r.type_of(ctx)?) {
+                        Some(fields) if fields.is_empty() => {
+                            let entry = list_element(&ann);
+                            let value_typ = entry.as_ref().and_then(record_type).and_then(|f| find_field(f, "mapValue"));
+                            match value_typ {
+                                Some(value_typ) if ctx.equivalent(&map_type(value_typ.to_owned()), &ann) => {
+                                    ctx.check_type(value_typ)?;
+                                    ann.clone()
+                                }
+                                _ => return Err(TypeError::InvalidToMap(format!("annotation {}", show(&ann))).into()),
+                            }
+                        }
+                        _ => t.type_of(ctx)?,
+                    },
+                    t => t.type_of(ctx)?,
+                };
+                ctx.check_eq(&ann, &typ)?;
+                Ok(ann)
+            }
+            Construct(t, fields) => {
+                // T::r = (T.default ⫽ r) : T.Type
+                let t = ast::Term::from(Expr::Term1(t.as_ref().to_owned()));
+                let field = |name| Term(ast::Term::FieldAccess(Box::new(t.clone()), name));
+                let record = Term(ast::Term::Record(fields.to_owned()));
+                let desugared = Ascribe(
+                    Box::new(Operation(Box::new(field("default")), "⫽", Box::new(record))),
+                    Box::new(Expr::Term1(field("Type"))),
+                );
+                desugared.type_of(ctx)
+            }
+            ImportAlt(_, _) => Err(TypeError::Unsupported(format!("unresolved import {}", show(self))).into()),
+        }
+    }
+}
+
+impl<'i> TypeCheck<'i> for Term<'i> {
+    fn type_of(&self, ctx: &mut TypeCtx<'i>) -> Result<Expr<'i>> {
+        use ast::Term::*;
+        match self {
+            Natural(_) => Ok(var("Natural")),
+            Integer(_) => Ok(var("Integer")),
+            Double(_) => Ok(var("Double")),
+            Bytes(_) => Ok(var("Bytes")),
+            &Var(name, idx) => ctx.lookup(name, idx),
+            Expr(e) => e.type_of(ctx),
+            Text(_, entries) => {
+                for val in entries.iter().filter_map(|(_, v)| v.as_ref()) {
+                    let typ = val.type_of(ctx)?;
+                    if !is_var(&typ, "Text") {
+                        return Err(TypeError::Mismatch("Text".to_owned(), show(&typ)).into());
+                    }
+                }
+                Ok(var("Text"))
+            }
+            List(vals) => {
+                let mut vals = vals.iter();
+                let typ = match vals.next() {
+                    Some(val) => val.type_of(ctx)?,
+                    None => return Err(TypeError::InvalidListType("[]".to_owned()).into()),
+                };
+                if ctx.universe_of(&typ)? != 0 {
+                    return Err(TypeError::InvalidListElement(show(&typ)).into());
+                }
+                for val in vals {
+                    let val_typ = val.type_of(ctx)?;
+                    ctx.check_eq(&typ, &val_typ)?;
+                }
+                Ok(app(var("List"), typ))
+            }
+            Record(fields) => {
+                let mut types = RecordData::new();
+                for (path, val) in fields {
+                    let typ = val.type_of(ctx)?;
+                    ctx.universe_of(&typ)?;
+                    let path: Vec<_> = path.iter().copied().collect();
+                    insert_path(&mut types, &path, typ)?;
+                }
+                sort_fields(&mut types);
+                Ok(TypeRecord(types).into())
+            }
+            TypeRecord(fields) => {
+                let mut u = 0;
+                for (i, (path, typ)) in fields.iter().enumerate() {
+                    if label(path).is_none() {
+                        return Err(TypeError::Unsupported(format!("record type field {}", show_path(path))).into());
+                    }
+                    if fields.iter().take(i).any(|(p, _)| p == path) {
+                        return Err(TypeError::DuplicateField(show_path(path)).into());
+                    }
+                    u = u.max(ctx.universe_of(typ)?);
+                }
+                Ok(universe_expr(u))
+            }
+            TypeEnum(alts) => {
+                let mut u = 0;
+                for (i, (name, typ)) in alts.iter().enumerate() {
+                    if alts.iter().take(i).any(|(n, _)| n == name) {
+                        return Err(TypeError::DuplicateField(name.to_string()).into());
+                    }
+                    if let Some(typ) = typ {
+                        u = u.max(ctx.universe_of(typ)?);
+                    }
+                }
+                Ok(universe_expr(u))
+            }
+            FieldAccess(t, name) => {
+                let typ = t.type_of(ctx)?;
+                if let Some(fields) = record_type(&typ) {
+                    return match find_field(fields, name) {
+                        Some(typ) => Ok(typ.to_owned()),
+                        None => Err(TypeError::MissingField(name.to_string(), show(&typ)).into()),
+                    };
+                }
+                if universe(&typ).is_none() {
+                    return Err(TypeError::NotARecord(show(t.as_ref()), show(&typ)).into());
+                }
+
+                let union = ctx.normalize(&t.as_ref().to_owned().into())?;
+                match type_enum(&union).map(|alts| alts.iter().find(|(n, _)| n == name)) {
+                    Some(Some((_, Some(arg_typ)))) => {
+                        let arg_typ = arg_typ.as_ref().to_owned();
+                        let mut union = union.clone();
+                        shift(&mut union, 1, name, 0);
+                        Ok(pi(Some(*name), arg_typ, union))
+                    }
+                    Some(Some((_, None))) => Ok(union),
+                    Some(None) => Err(TypeError::MissingField(name.to_string(), show(&union)).into()),
+                    None => Err(TypeError::NotAUnion(show(&union)).into()),
+                }
+            }
+            Project(1, t, names) => {
+                let typ = t.type_of(ctx)?;
+                let fields = match record_type(&typ) {
+                    Some(fields) => fields,
+                    None => return Err(TypeError::NotARecord(show(t.as_ref()), show(&typ)).into()),
+                };
+                let mut types = RecordData::new();
+                for name in names {
+                    let name = match name {
+                        Term1::Term(Var(name, 0)) => *name,
+                        o => return Err(TypeError::Unsupported(format!("projection by {}", show(o))).into()),
+                    };
+                    match find_field(fields, name) {
+                        Some(typ) => types.push_back((ast::path([name]), Box::new(typ.to_owned()))),
+                        None => return Err(TypeError::MissingField(name.to_owned(), show(&typ)).into()),
+                    }
+                }
+                sort_fields(&mut types);
+                Ok(TypeRecord(types).into())
+            }
+            Project(_, t, selector) => {
+                let typ = t.type_of(ctx)?;
+                let fields = match record_type(&typ) {
+                    Some(fields) => fields,
+                    None => return Err(TypeError::NotARecord(show(t.as_ref()), show(&typ)).into()),
+                };
+                let selector = match selector.len() {
+                    1 => ast::Expr::Term1(selector[0].to_owned()),
+                    _ => return Err(TypeError::Unsupported(format!("selection by {:?}", selector)).into()),
+                };
+                ctx.universe_of(&selector)?;
+                let selector = ctx.normalize(&selector)?;
+                let selected = match record_type(&selector) {
+                    Some(selected) => selected,
+                    None => return Err(TypeError::NotARecordType(show(&selector)).into()),
+                };
+                for (path, sel_typ) in selected {
+                    let name = label(path).unwrap_or_default();
+                    match find_field(fields, name) {
+                        Some(typ) => ctx.check_eq(sel_typ, typ)?,
+                        None => return Err(TypeError::MissingField(name.to_owned(), show(&typ)).into()),
+                    }
+                }
+                Ok(selector)
+            }
+            Merge(handlers, t) => merge_type(ctx, handlers, t)?
+                .ok_or_else(|| TypeError::InvalidMerge("empty union without a type annotation".to_owned()).into()),
+            ToMap(t) => {
+                let typ = t.type_of(ctx)?;
+                let fields = match record_type(&typ) {
+                    Some(fields) => fields,
+                    None => return Err(TypeError::NotARecord(show(t.as_ref()), show(&typ)).into()),
+                };
+                let mut types = fields.iter().map(|(_, typ)| typ.as_ref());
+                let value_typ = match types.next() {
+                    Some(value_typ) => value_typ,
+                    None => return Err(TypeError::InvalidToMap("empty record without a List annotation".to_owned()).into()),
+                };
+                for typ in types {
+                    ctx.check_eq(value_typ, typ)?;
+                }
+                ctx.check_type(value_typ)?;
+                Ok(map_type(value_typ.to_owned()))
+            }
+            ShowConstructor(t) => {
+                let typ = t.type_of(ctx)?;
+                match (type_enum(&typ), optional_element(&typ)) {
+                    (None, None) => Err(TypeError::NotAUnion(show(&typ)).into()),
+                    _ => Ok(var("Text")),
+                }
+            }
+            Import(_) => Err(TypeError::Unsupported(format!("unresolved import {}", show(self))).into()),
+            Embed(code) => Err(TypeError::Unsupported(format!("embedded {}", code)).into()),
+            Path(_) => Err(TypeError::Unsupported(format!("path {}", show(self))).into()),
+        }
+    }
+}
+
+impl<'i, T: TypeCheck<'i>> TypeCheck<'i> for Box<T> {
+    fn type_of(&self, ctx: &mut TypeCtx<'i>) -> Result<Expr<'i>> {
+        T::type_of(self.as_ref(), ctx).map_err(|e| e.at(ctx.locate(self.as_ref())))
+    }
+}
+
+fn type_of_operation<'i>(
+    ctx: &mut TypeCtx<'i>,
+    (a, a_typ): (&Term1<'i>, &Expr<'i>),
+    op: &str,
+    (b, b_typ): (&Term1<'i>, &Expr<'i>),
+) -> Result<Expr<'i>> {
+    let invalid = || -> Error { TypeError::InvalidOperands(op.to_owned(), show(a_typ), show(b_typ)).into() };
+    let both = |name| is_var(a_typ, name) && is_var(b_typ, name);
+
+    match op_name(op) {
+        "+" | "*" if both("Natural") => Ok(var("Natural")),
+        "++" if both("Text") => Ok(var("Text")),
+        "&&" | "||" | "==" | "!=" if both("Bool") => Ok(var("Bool")),
+        "#" if list_element(a_typ).is_some() => {
+            ctx.check_eq(a_typ, b_typ)?;
+            Ok(a_typ.to_owned())
+        }
+        "⫽" => match (record_type(a_typ), record_type(b_typ)) {
+            (Some(a_fields), Some(b_fields)) => {
+                let mut fields: RecordData = a_fields
+                    .iter()
+                    .filter(|(p, _)| find_field(b_fields, label(p).unwrap_or_default()).is_none())
+                    .cloned()
+                    .collect();
+                fields.extend(b_fields.iter().cloned());
+                sort_fields(&mut fields);
+                Ok(Term::TypeRecord(fields).into())
+            }
+            _ => Err(invalid()),
+        },
+        "∧" => match (record_type(a_typ), record_type(b_typ)) {
+            (Some(a_fields), Some(b_fields)) => Ok(Term::TypeRecord(combine_types(a_fields, b_fields)?).into()),
+            _ => Err(invalid()),
+        },
+        "⩓" => {
+            let (a, b) = (Expr::Term1(a.to_owned()), Expr::Term1(b.to_owned()));
+            let u = match (universe(a_typ), universe(b_typ)) {
+                (Some(u1), Some(u2)) => u1.max(u2),
+                _ => return Err(invalid()),
+            };
+            let (a, b) = (ctx.normalize(&a)?, ctx.normalize(&b)?);
+            match (record_type(&a), record_type(&b)) {
+                (Some(a_fields), Some(b_fields)) => combine_types(a_fields, b_fields)?,
+                _ => return Err(invalid()),
+            };
+            Ok(universe_expr(u))
+        }
+        "≡" => {
+            ctx.check_eq(a_typ, b_typ)?;
+            ctx.check_type(a_typ)?;
+            Ok(var("Type"))
+        }
+        "+" | "*" | "++" | "&&" | "||" | "==" | "!=" | "#" => Err(invalid()),
+        op => Err(TypeError::Unsupported(format!("operator {}", op)).into()),
+    }
+}
+
+/// The type of `merge handlers t`, if the union is not empty.
+fn merge_type<'i>(ctx: &mut TypeCtx<'i>, handlers: &RecordData<'i>, t: &Term<'i>) -> Result<Option<Expr<'i>>> {
+    let union = t.type_of(ctx)?;
+    let optional_alts: TypeEnumData;
+    let alts = match (type_enum(&union), optional_element(&union)) {
+        (Some(alts), _) => alts,
+        // Optionals merge as < None | Some : A >
+        (None, Some(elem)) => {
+            optional_alts = vec![("None", None), ("Some", Some(Box::new(elem)))].into();
+            &optional_alts
+        }
+        (None, None) => return Err(TypeError::NotAUnion(show(&union)).into()),
+    };
+    let handlers_typ = Term::Record(handlers.to_owned()).type_of(ctx)?;
+    let handlers = record_type(&handlers_typ).unwrap();
+
+    if let Some((path, _)) = handlers
+        .iter()
+        .find(|(p, _)| !alts.iter().any(|(n, _)| label(p) == Some(*n)))
+    {
+        return Err(TypeError::InvalidMerge(format!("unused handler .{}", show_path(path))).into());
+    }
+
+    let mut result = None;
+    for (name, arg_typ) in alts {
+        let handler = match find_field(handlers, name) {
+            Some(handler) => handler,
+            None => return Err(TypeError::InvalidMerge(format!("missing handler .{}", name)).into()),
+        };
+        let typ = match (arg_typ, handler) {
+            (Some(arg_typ), ast::Expr::Term1(Term1::Arrow(x, h_arg_typ, typ))) => {
+                ctx.check_eq(h_arg_typ, arg_typ)?;
+                let x = x.unwrap_or("_");
+                if occurs(typ, x, 0) {
+                    return Err(TypeError::InvalidMerge(format!("handler .{} has a dependent type", name)).into());
+                }
+                let mut typ = typ.as_ref().to_owned();
+                shift(&mut typ, -1, x, 0);
+                typ
+            }
+            (Some(_), handler) => {
+                return Err(TypeError::InvalidMerge(format!("handler .{} is not a function: {}", name, show(handler))).into());
+            }
+            (None, handler) => handler.to_owned(),
+        };
+        match &result {
+            Some(result) => ctx.check_eq(result, &typ)?,
+            None => result = Some(typ),
+        }
+    }
+    Ok(result)
+}
+
+/// The type of `r with path = v`, given the type of `r` and `v`.
+fn with_type<'i>(ctx: &TypeCtx<'i>, typ: Expr<'i>, path: &[Ident<'i>], val_typ: Expr<'i>) -> Result<Expr<'i>> {
+    if path[0] == "?" {
+        let elem = match optional_element(&typ) {
+            Some(elem) => elem,
+            None => return Err(TypeError::Mismatch("an Optional".to_owned(), show(&typ)).into()),
+        };
+        let elem_typ = match &path[1..] {
+            [] => val_typ,
+            rest => with_type(ctx, elem.clone(), rest, val_typ)?,
+        };
+        ctx.check_eq(&elem, &elem_typ)?;
+        return Ok(typ);
+    }
+    let mut fields = match record_type(&typ) {
+        Some(fields) => fields.to_owned(),
+        None => return Err(TypeError::NotARecordType(show(&typ)).into()),
+    };
+    let (name, rest) = (path[0], &path[1..]);
+    let pos = fields.iter().position(|(p, _)| label(p) == Some(name));
+
+    let field_typ = match rest {
+        [] => val_typ,
+        rest => {
+            let inner = match pos {
+                Some(pos) => fields[pos].1.as_ref().to_owned(),
+                None => Term::TypeRecord(<_>::default()).into(),
+            };
+            with_type(ctx, inner, rest, val_typ)?
+        }
+    };
+    match pos {
+        Some(pos) => *fields[pos].1 = field_typ,
+        None => fields.push_back((ast::path([name]), Box::new(field_typ))),
+    }
+    sort_fields(&mut fields);
+    Ok(Term::TypeRecord(fields).into())
+}
+
+/// Recursively merge two record types, as `∧` and `⩓` do.
+fn combine_types<'i>(a: &RecordData<'i>, b: &RecordData<'i>) -> Result<RecordData<'i>> {
+    let mut fields = a.to_owned();
+    for (path, b_typ) in b {
+        let name = label(path).unwrap_or_default();
+        match fields.iter_mut().find(|(p, _)| label(p) == Some(name)) {
+            Some((_, a_typ)) => match (record_type(a_typ), record_type(b_typ)) {
+                (Some(a_inner), Some(b_inner)) => {
+                    let inner = combine_types(a_inner, b_inner)?;
+                    **a_typ = Term::TypeRecord(inner).into();
+                }
+                _ => return Err(TypeError::FieldCollision(name.to_owned()).into()),
+            },
+            None => fields.push_back((path.to_owned(), b_typ.to_owned())),
+        }
+    }
+    sort_fields(&mut fields);
+    Ok(fields)
+}
+
+/// Insert a (possibly dotted) field type into a record type.
+fn insert_path<'i>(fields: &mut RecordData<'i>, path: &[Ident<'i>], typ: Expr<'i>) -> Result<()> {
+    let (name, rest) = (path[0], &path[1..]);
+    let existing = fields.iter_mut().find(|(p, _)| label(p) == Some(name));
+    match (existing, rest) {
+        (None, []) => fields.push_back((ast::path([name]), Box::new(typ))),
+        (None, rest) => {
+            let mut inner = RecordData::new();
+            insert_path(&mut inner, rest, typ)?;
+            fields.push_back((ast::path([name]), Box::new(Term::TypeRecord(inner).into())));
+        }
+        (Some((_, inner)), rest) if !rest.is_empty() => match inner.as_mut() {
+            Expr::Term1(Term1::Term(Term::TypeRecord(inner))) => {
+                insert_path(inner, rest, typ)?;
+                sort_fields(inner);
+            }
+            _ => return Err(TypeError::DuplicateField(name.to_owned()).into()),
+        },
+        (Some(_), _) => return Err(TypeError::DuplicateField(name.to_owned()).into()),
+    }
+    Ok(())
+}
+
+/// Substitute the normal forms of the let-bound names of `gamma` into `expr`.
+fn unfold<'i>(gamma: &[Binding<'i>], expr: &mut Expr<'i>) -> Result<()> {
+    let mut result = Ok(());
+    expr.visit_vars(&mut <_>::default(), &mut |t: &mut Term<'i>, bound: &[Ident<'i>]| {
+        let (name, idx) = match *t {
+            Term::Var(name, idx) if idx >= count(bound, name) => (name, idx - count(bound, name)),
+            _ => return,
+        };
+        let k = match binder(gamma, name, idx) {
+            Some(k) => k,
+            None => return,
+        };
+        match normal_value(gamma, k) {
+            Ok(Some(mut val)) => {
+                for b in &gamma[k..] {
+                    shift(&mut val, 1, b.name, 0);
+                }
+                for l in bound {
+                    shift(&mut val, 1, l, 0);
+                }
+                *t = val.into();
+            }
+            Ok(None) => (),
+            Err(e) if result.is_ok() => result = Err(e),
+            Err(_) => (),
+        }
+    });
+    result
+}
+
+/// The position in `gamma` of the binder of `name@idx`.
+fn binder(gamma: &[Binding], name: &str, idx: u16) -> Option<usize> {
+    gamma
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, b)| b.name == name)
+        .nth(idx as usize)
+        .map(|(k, _)| k)
+}
+
+/// Normalize `expr` in `gamma`, by eval.
+fn normalize_in<'i>(gamma: &[Binding<'i>], expr: &Expr<'i>) -> Result<Expr<'i>> {
+    let mut expr = expr.to_owned();
+    unfold(gamma, &mut expr)?;
+    let names: Vec<_> = gamma.iter().map(|b| b.name).collect();
+    crate::normalize(&names, &expr)
+}
+
+/// The normal form of the `k`-th let-bound value, in its own context.
+fn normal_value<'i>(gamma: &[Binding<'i>], k: usize) -> Result<Option<Expr<'i>>> {
+    let b = &gamma[k];
+    let val = match &b.value {
+        Some(val) => val,
+        None => return Ok(None),
+    };
+    if b.normal.borrow().is_none() {
+        let normal = normalize_in(&gamma[..k], val)?;
+        *b.normal.borrow_mut() = Some(normal);
+    }
+    Ok(b.normal.borrow().to_owned())
+}
+
+fn occurs<'i>(e: &Expr<'i>, name: &str, idx: u16) -> bool {
+    let mut found = false;
+    e.to_owned().visit_vars(&mut <_>::default(), &mut |t: &mut Term<'i>, bound: &[Ident<'i>]| {
+        found |= matches!(t, &mut Term::Var(n, i) if n == name && i == idx + count(bound, name));
+    });
+    found
+}
+
+fn strip_expr<'a, 'i>(mut e: &'a Expr<'i>) -> &'a Expr<'i> {
+    while let Expr::Term1(Term1::Term(Term::Expr(inner))) = e {
+        e = inner;
+    }
+    e
+}
+
+fn strip_term1<'a, 'i>(mut t: &'a Term1<'i>) -> &'a Term1<'i> {
+    while let Term1::Term(Term::Expr(inner)) = t {
+        match inner.as_ref() {
+            Expr::Term1(inner) => t = inner,
+            _ => break,
+        }
+    }
+    t
+}
+
+/// The unicode name of an operator.
+fn op_name(op: &str) -> &str {
+    match op {
+        "//" => "⫽",
+        "/\\" => "∧",
+        "//\\\\" => "⩓",
+        "===" => "≡",
+        op => op,
+    }
+}
+
+trait Shown {
+    fn shown(&self) -> String;
+}
+
+impl<'i> Shown for Expr<'i> {
+    fn shown(&self) -> String {
+        format!("{}", Show(self))
+    }
+}
+
+impl<'i> Shown for Term1<'i> {
+    fn shown(&self) -> String {
+        format!("{}", Show(self))
+    }
+}
+
+impl<'i> Shown for Term<'i> {
+    fn shown(&self) -> String {
+        format!("{}", Show(self))
+    }
+}
+
+fn show<T: Shown>(t: &T) -> String {
+    t.shown()
+}
+
+fn show_path(path: &Path) -> String {
+    path.iter().copied().collect::<Vec<_>>().join(".")
+}
+
+fn label<'i>(path: &Path<'i>) -> Option<Ident<'i>> {
+    match path.len() {
+        1 => path.front().copied(),
+        _ => None,
+    }
+}
+
+fn sort_fields(fields: &mut RecordData) {
+    fields.make_contiguous().sort_by(|(a, _), (b, _)| a.cmp(b));
+}
+
+fn find_field<'a, 'i>(fields: &'a RecordData<'i>, name: &str) -> Option<&'a Expr<'i>> {
+    fields
+        .iter()
+        .find(|(p, _)| label(p) == Some(name))
+        .map(|(_, v)| v.as_ref())
+}
+
+fn var<'i>(name: Ident<'i>) -> Expr<'i> {
+    Term::Var(name, 0).into()
+}
+
+fn is_var(e: &Expr, name: &str) -> bool {
+    matches!(strip_expr(e), Expr::Term1(Term1::Term(Term::Var(n, 0))) if *n == name)
+}
+
+fn universe(e: &Expr) -> Option<usize> {
+    UNIVERSES.iter().position(|u| is_var(e, u))
+}
+
+fn universe_expr<'i>(u: usize) -> Expr<'i> {
+    var(UNIVERSES[u])
+}
+
+fn app<'i>(f: Expr<'i>, a: Expr<'i>) -> Expr<'i> {
+    let f = match f {
+        Expr::Term1(f @ (Term1::Term(_) | Term1::Evaluation(_, _))) => f,
+        f => Term1::Term(Term::Expr(Box::new(f))),
+    };
+    Expr::Term1(Term1::Evaluation(Box::new(f), a.into()))
+}
+
+/// `List { mapKey : Text, mapValue : T }`
+fn map_type(value_typ: Expr) -> Expr {
+    let entry = Term::TypeRecord(
+        vec![
+            (ast::path(["mapKey"]), Box::new(var("Text"))),
+            (ast::path(["mapValue"]), Box::new(value_typ)),
+        ]
+        .into(),
+    );
+    app(var("List"), entry.into())
+}
+
+fn pi<'i>(name: Option<Ident<'i>>, a: Expr<'i>, b: Expr<'i>) -> Expr<'i> {
+    Expr::Term1(Term1::Arrow(name, Box::new(a), Box::new(b)))
+}
+
+fn list_element<'i>(typ: &Expr<'i>) -> Option<Expr<'i>> {
+    type_argument(typ, "List")
+}
+
+fn optional_element<'i>(typ: &Expr<'i>) -> Option<Expr<'i>> {
+    type_argument(typ, "Optional")
+}
+
+/// `A`, if `typ` is `name A`.
+fn type_argument<'i>(typ: &Expr<'i>, name: &str) -> Option<Expr<'i>> {
+    match strip_expr(typ) {
+        Expr::Term1(Term1::Evaluation(f, elem)) if matches!(strip_term1(f), &Term1::Term(Term::Var(n, 0)) if n == name) => {
+            Some(elem.to_owned().into())
+        }
+        _ => None,
+    }
+}
+
+fn record_type<'a, 'i>(typ: &'a Expr<'i>) -> Option<&'a RecordData<'i>> {
+    match strip_expr(typ) {
+        Expr::Term1(Term1::Term(Term::TypeRecord(fields))) => Some(fields),
+        _ => None,
+    }
+}
+
+fn type_enum<'a, 'i>(typ: &'a Expr<'i>) -> Option<&'a TypeEnumData<'i>> {
+    match strip_expr(typ) {
+        Expr::Term1(Term1::Term(Term::TypeEnum(alts))) => Some(alts),
+        _ => None,
+    }
+}
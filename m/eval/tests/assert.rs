@@ -0,0 +1,48 @@
+/// The error checking `src` (as `test.dhall`) fails with.
+fn typecheck_error(src: &str) -> Option<String> {
+    let mut spans = parse::Spans::new("test.dhall", src);
+    let expr = parse::parse_spanned(&mut spans, src).unwrap();
+    let mut ctx = eval::TypeCtx::new().with_spans(&spans);
+    eval::TypeCheck::type_of(&expr, &mut ctx).err().map(|e| e.to_string())
+}
+
+/// The error evaluating `src` (as `test.dhall`) fails with.
+fn eval_error(src: &str) -> Option<String> {
+    let mut spans = parse::Spans::new("test.dhall", src);
+    let mut expr = parse::parse_spanned(&mut spans, src).unwrap();
+    let mut ctx = eval::ctx().with_spans(&spans);
+    eval::eval(&mut ctx, &mut expr).err().map(|e| e.to_string())
+}
+
+#[test]
+fn equivalent_sides_pass() {
+    for src in &[
+        "assert : 1 + 1 ≡ 2",
+        "assert : Natural/even 4 === True",
+        "assert : (λ(x : Natural) → x) ≡ (λ(y : Natural) → y)",
+        "λ(n : Natural) → assert : n + 0 ≡ n",
+        "let xs = [1, 2] in assert : List/length Natural xs ≡ 2",
+        "assert : { b = 1, a = 2 } ≡ { a = 2, b = 1 }",
+    ] {
+        assert_eq!(typecheck_error(src), None, "{}", src);
+        assert_eq!(eval_error(src), None, "{}", src);
+    }
+}
+
+#[test]
+fn typecheck_rejects_failed_assertions() {
+    let err = typecheck_error("let x = 1\nin  assert : x + 1 ≡ 3").unwrap();
+    assert_eq!(err, "test.dhall:2:14: type: assertion failed: 2 is not equivalent to 3");
+
+    let err = typecheck_error("λ(n : Natural) → assert : n ≡ 0").unwrap();
+    assert!(err.contains("assertion failed: n is not equivalent to 0"), "{}", err);
+
+    let err = typecheck_error("assert : Natural").unwrap();
+    assert!(err.contains("not an equivalence"), "{}", err);
+}
+
+#[test]
+fn eval_locates_failed_assertions() {
+    let err = eval_error("let x = 1\nin  assert : x + 1 ≡ 3").unwrap();
+    assert_eq!(err, "test.dhall:2:14: eval: assertion failed: 2 is not equivalent to 3");
+}
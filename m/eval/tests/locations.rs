@@ -0,0 +1,19 @@
+/// The error evaluating `src` (as `test.dhall`) fails with.
+fn eval_error(src: &str) -> String {
+    let mut spans = parse::Spans::new("test.dhall", src);
+    let mut expr = parse::parse_spanned(&mut spans, src).unwrap();
+    let mut ctx = eval::ctx().with_spans(&spans);
+    eval::eval(&mut ctx, &mut expr).err().unwrap().to_string()
+}
+
+#[test]
+fn eval_errors_are_located_at_the_failing_term() {
+    let err = eval_error("let f = 1\nin  f 2");
+    assert!(err.starts_with("test.dhall:2:5: eval: "), "{}", err);
+
+    let err = eval_error("let r = { a = 1 }\nin  r.a + r.b");
+    assert!(err.starts_with("test.dhall:2:11: eval: "), "{}", err);
+
+    let err = eval_error("[ 1\n, y\n]");
+    assert!(err.starts_with("test.dhall:2:3: eval: "), "{}", err);
+}
@@ -0,0 +1,81 @@
+/// The normal form of `src`, shown.
+fn normalize(src: &str) -> String {
+    let mut ctx = eval::ctx();
+    let mut expr = parse::parse_str(src).unwrap();
+    eval::eval(&mut ctx, &mut expr).unwrap();
+    eval::Show(&expr).to_string().trim().to_owned()
+}
+
+#[test]
+fn natural_build_of_fold_is_fused() {
+    assert_eq!(normalize("λ(n : Natural) → Natural/build (Natural/fold n)"), "\\(n : Natural) -> n");
+    assert_eq!(normalize("Natural/build (Natural/fold 3)"), "3");
+}
+
+#[test]
+fn list_build_of_fold_is_fused() {
+    assert_eq!(
+        normalize("λ(a : Type) → λ(xs : List a) → List/build a (List/fold a xs)"),
+        "\\(a : Type) -> \\(xs : List a) -> xs"
+    );
+}
+
+#[test]
+fn builtins_reduce_on_literals() {
+    assert_eq!(normalize("Natural/fold 3 Natural (λ(x : Natural) → x * 2) 1"), "8");
+    assert_eq!(normalize("List/fold Natural [1, 2, 3] Natural (λ(x : Natural) → λ(y : Natural) → x + y) 0"), "6");
+    assert_eq!(normalize("Natural/subtract 2 5"), "3");
+    assert_eq!(normalize("Integer/show +3"), "\"+3\"");
+    assert_eq!(normalize("List/length Bool [True, False]"), "2");
+}
+
+#[test]
+fn records_and_unions_are_sorted() {
+    assert_eq!(normalize("{ b = 1, a = 2 }"), normalize("{ a = 2, b = 1 }"));
+    assert_eq!(normalize("{ b : Bool, a : Natural }"), normalize("{ a : Natural, b : Bool }"));
+    assert_eq!(normalize("< B | A : Bool >"), normalize("< A : Bool | B >"));
+}
+
+#[test]
+fn missing_does_not_evaluate() {
+    let mut ctx = eval::ctx();
+    let mut expr = parse::parse_str("missing").unwrap();
+    let err = match eval::eval(&mut ctx, &mut expr) {
+        Ok(_) => panic!("missing evaluated to {}", eval::Show(&expr)),
+        Err(err) => err,
+    };
+    assert!(err.to_string().contains("missing"), "{}", err);
+}
+
+#[test]
+fn completions_nest_dotted_fields() {
+    let src = "let T = { Type = { a : { b : Natural } }, default = { a = { b = 1 } } } in T::{ a.b = 5 }";
+    let typ = eval::typecheck(&parse::parse_str(src).unwrap()).unwrap();
+    assert_eq!(eval::Show(&typ).to_string().trim(), normalize("{ a : { b : Natural } }"));
+    assert_eq!(normalize(src), "{ , a = { , b = 5 } }");
+}
+
+/// The error evaluating `src` fails with.
+fn eval_error(src: &str) -> String {
+    let mut ctx = eval::ctx();
+    let mut expr = parse::parse_str(src).unwrap();
+    match eval::eval(&mut ctx, &mut expr) {
+        Ok(_) => panic!("{} evaluated to {}", src, eval::Show(&expr)),
+        Err(err) => err.to_string(),
+    }
+}
+
+#[test]
+fn combining_non_records_is_a_collision() {
+    assert!(eval_error("{ x = True } ∧ { x = False }").contains("field collision"));
+    assert!(eval_error("{ x : Natural } ⩓ { x : Natural }").contains("field collision"));
+    assert!(eval_error("{ x = { y = 1 } } ∧ { x = { y = 2 } }").contains("field collision"));
+}
+
+#[test]
+fn combining_unknown_fields_is_deferred() {
+    assert_eq!(
+        normalize("λ(r : { y : Natural }) → { x = r } ∧ { x = { z = 1 } }"),
+        "\\(r : { , y : Natural }) -> { , x = r ∧ { , z = 1 } }"
+    );
+}
@@ -0,0 +1,63 @@
+/// The type of `src`, shown, or the error.
+fn type_of(src: &str) -> Result<String, String> {
+    let expr = parse::parse_str(src).unwrap();
+    match eval::typecheck(&expr) {
+        Ok(typ) => Ok(eval::Show(&typ).to_string().trim().to_owned()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Check `src` against the `expected` type, by annotating it.
+fn assert_type(src: &str, expected: &str) {
+    let annotated = format!("({}) : {}", src, expected);
+    assert!(type_of(&annotated).is_ok(), "{}: {:?}", annotated, type_of(&annotated));
+}
+
+fn assert_error(src: &str, expected: &str) {
+    match type_of(src) {
+        Ok(typ) => panic!("{} has type {}", src, typ),
+        Err(err) => assert!(err.contains(expected), "{}: {}", src, err),
+    }
+}
+
+#[test]
+fn literals_and_builtins() {
+    assert_type("1", "Natural");
+    assert_type("[+1, -2]", "List Integer");
+    assert_type("\"a${\"b\"}\"", "Text");
+    assert_type("Natural/fold", "Natural → ∀(n : Type) → (n → n) → n → n");
+    assert_type("Some True", "Optional Bool");
+    assert_type("Type", "Kind");
+}
+
+#[test]
+fn types_are_equal_up_to_bound_names() {
+    assert_type("λ(a : Type) → λ(x : a) → x", "∀(b : Type) → ∀(y : b) → b");
+    assert_type("{ b = True, a = 1 }", "{ a : Natural, b : Bool }");
+    assert_type("< A | B : Bool >.B", "Bool → < B : Bool | A >");
+}
+
+#[test]
+fn let_bound_names_are_unfolded() {
+    assert_type("let T = Natural in λ(x : T) → x", "Natural → Natural");
+    assert_type("let T = Natural let U = List T in [1] : U", "List Natural");
+    assert_type("λ(T : Type) → let U = T in λ(x : U) → x", "∀(T : Type) → T → T");
+}
+
+#[test]
+fn types_are_normalized_by_eval() {
+    assert_type("λ(x : ({ T = Bool } with T = Natural).T) → x + 1", "Natural → Natural");
+    assert_type("[1, 2] : List (merge { A = Natural, B = Bool } < A | B >.A)", "List Natural");
+    assert_type("{ a = 1 } : { a : Natural } ⩓ {}", "{ a : Natural }");
+}
+
+#[test]
+fn mismatches_are_errors() {
+    assert_error("1 : Bool", "type mismatch");
+    assert_error("[1, True]", "type mismatch");
+    assert_error("(λ(x : Natural) → x) True", "type mismatch");
+    assert_error("if 1 then 2 else 3", "not a Bool");
+    assert_error("x", "unbound variable");
+    assert_error("{ a = 1 }.b", "missing field");
+    assert_error("λ(x : Natural) → x x", "not a function");
+}
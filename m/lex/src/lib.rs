@@ -41,14 +41,16 @@ impl<'s> Lex<'s> {
         let inp = self.src();
 
         parse_whitespace(inp)
+            .or_else(|| parse_bytes(inp))
             .or_else(|| parse_double(inp))
             .or_else(|| parse_natural_integer(inp))
             .or_else(|| parse_negative_integer(inp))
             .or_else(|| parse_block_comment(inp))
             .or_else(|| parse_line_comment1(inp))
             .or_else(|| parse_line_comment2(inp))
-            .or_else(|| parse_rel_uri(inp))
+            .or_else(|| parse_local_uri(inp))
             .or_else(|| parse_http_uri(inp))
+            .or_else(|| parse_env_uri(inp))
             .or_else(|| parse_sha256(inp))
             .or_else(|| parse_punctuation(inp))
             .or_else(|| parse_ident_or_keyword(inp))
@@ -105,7 +107,7 @@ impl<'s> Iterator for Lex<'s> {
     fn next(&mut self) -> Option<Self::Item> {
         let mut token = self
             .next_moody()
-            .map(|t| (0, t, t.as_str().as_bytes().len()));
+            .map(|t| (0, t, t.as_str().len()));
 
         token.as_mut().into_iter().for_each(|span| {
             span_shift(&self.last_span, span);
@@ -136,6 +138,7 @@ where
 }
 
 type R<'s> = Option<Token<'s>>;
+type ToToken = fn(&str) -> Token;
 
 fn range_parse<T, P>(inp: &str, to_token: T, pred: P) -> R<'_>
 where
@@ -177,6 +180,17 @@ fn parse_whitespace(inp: &str) -> R<'_> {
     range_parse(inp, |s| Token::Whitespace(s), |(_, c)| c.is_whitespace())
 }
 
+/// `0x"…"`, with an even number of hex digits.
+fn parse_bytes(inp: &str) -> R<'_> {
+    let rest = inp.strip_prefix("0x\"")?;
+    let len = rest.find(|c: char| !c.is_ascii_hexdigit()).unwrap_or(rest.len());
+    if rest[len..].starts_with('"') && len % 2 == 0 {
+        Some(Token::Bytes(&inp[..3 + len + 1]))
+    } else {
+        None
+    }
+}
+
 fn parse_natural_integer(inp: &str) -> R<'_> {
     range_parse(
         inp,
@@ -199,44 +213,74 @@ fn parse_negative_integer(inp: &str) -> R<'_> {
 }
 
 fn parse_double(inp: &str) -> R<'_> {
-    let mut p = '_';
-    let mut q = '_';
-    scan_parse(
-        inp,
-        |s| Token::Double(s),
-        move |c| {
-            let n = match (q, p, c) {
-                (q, p, c) if q.is_ascii_digit() && p.is_ascii_digit() && c.is_ascii_digit() => 1,
-                (q, p, '.') if q.is_ascii_digit() && p.is_ascii_digit() => 1,
-                ('-', p, c) if p.is_ascii_digit() && c.is_ascii_digit() => 1,
-                ('-', p, '.') if p.is_ascii_digit() => 1,
-                (q, '.', c) if q.is_ascii_digit() && c.is_ascii_digit() => 1,
-                ('_', '-', c) if c.is_ascii_digit() => 1,
-                ('_', p, c) if p.is_ascii_digit() && c.is_ascii_digit() => 1,
-                ('_', p, '.') if p.is_ascii_digit() => 1,
-                ('_', '_', '-') => 1,
-                ('_', '_', c) if c.is_ascii_digit() => 1,
-                _ => return None,
-            };
-            q = p;
-            p = c;
-            Some(n)
-        },
-    )
-    .and_then(longer_than(2))
+    for special in ["NaN", "Infinity", "-Infinity"] {
+        if let Some(rest) = inp.strip_prefix(special) {
+            if !rest.starts_with(|c: char| c.is_alphanumeric() || "_-/".contains(c)) {
+                return Some(Token::Double(&inp[..special.len()]));
+            }
+        }
+    }
+
+    let bytes = inp.as_bytes();
+    let digits = |from: usize| bytes[from.min(bytes.len())..].iter().take_while(|b| b.is_ascii_digit()).count();
+
+    let mut len = matches!(bytes.first(), Some(b'+' | b'-')) as usize;
+    let whole = digits(len);
+    if whole == 0 {
+        return None;
+    }
+    len += whole;
+
+    let mut is_double = false;
+    if bytes.get(len) == Some(&b'.') {
+        let fraction = digits(len + 1);
+        if fraction == 0 {
+            return None;
+        }
+        len += 1 + fraction;
+        is_double = true;
+    }
+    if let Some(b'e' | b'E') = bytes.get(len) {
+        let sign = matches!(bytes.get(len + 1), Some(b'+' | b'-')) as usize;
+        let exponent = digits(len + 1 + sign);
+        if exponent > 0 {
+            len += 1 + sign + exponent;
+            is_double = true;
+        }
+    }
+
+    if is_double {
+        Some(Token::Double(&inp[..len]))
+    } else {
+        None
+    }
 }
 
-fn parse_rel_uri(inp: &str) -> R<'_> {
-    range_parse(
-        inp,
-        |s| Token::RelUri(s),
-        |&(i, c)| {
-            (i == 0 && c == '.')
-                || (i == 1 && (c == '.' || c == '/'))
-                || (i >= 2 && !c.is_whitespace() && c != ')' && c != ',')
-        },
-    )
-    .and_then(longer_than(2))
+/// A relative (`./`, `../`), home-relative (`~/`) or absolute (`/`) path.
+fn parse_local_uri(inp: &str) -> R<'_> {
+    const PREFIXES: &[(&str, ToToken)] = &[
+        ("../", |s| Token::RelUri(s)),
+        ("./", |s| Token::RelUri(s)),
+        ("~/", |s| Token::HomeUri(s)),
+        ("/", |s| Token::AbsUri(s)),
+    ];
+
+    let &(prefix, to_token) = PREFIXES.iter().find(|(p, _)| inp.starts_with(p))?;
+    let rest = &inp[prefix.len()..];
+    // A path starts with a component, which keeps `//` and `/\` operators.
+    if !rest.starts_with(is_path_char) {
+        return None;
+    }
+    let len = rest
+        .find(|c| !(is_path_char(c) || c == '/'))
+        .unwrap_or(rest.len());
+    Some(to_token(&inp[..prefix.len() + len]))
+}
+
+fn is_path_char(c: char) -> bool {
+    !c.is_ascii()
+        || matches!(c, '!' | '$'..='\'' | '*' | '+' | '-' | '.' | '0'..=';' | '=' | '@'..='Z')
+        || matches!(c, '^'..='z' | '|' | '~')
 }
 
 fn parse_http_uri(inp: &str) -> R<'_> {
@@ -250,13 +294,43 @@ fn parse_http_uri(inp: &str) -> R<'_> {
                 || (i == 3 && c == 'p')
                 || (i == 4 && (c == ':' || c == 's'))
                 || (i == 5 && (c == '/' || c == ':'))
-                || (i == 6 && (c == '/' || c == '/'))
+                || (i == 6 && c == '/')
                 || (i >= 7 && !c.is_whitespace() && c != ')')
         },
     )
     .and_then(longer_than(7))
 }
 
+/// `env:NAME`, or `env:"NAME"` with escapes.
+fn parse_env_uri(inp: &str) -> R<'_> {
+    let rest = inp.strip_prefix("env:")?;
+    let len = match rest.strip_prefix('"') {
+        Some(quoted) => {
+            let mut escaped = false;
+            let end = quoted.find(|c| match (escaped, c) {
+                (false, '"') => true,
+                (false, '\\') => {
+                    escaped = true;
+                    false
+                }
+                _ => {
+                    escaped = false;
+                    false
+                }
+            })?;
+            end + 2
+        }
+        None => {
+            if !rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                return None;
+            }
+            rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len())
+        }
+    };
+    Some(Token::EnvUri(&inp[..4 + len]))
+}
+
 fn parse_sha256(inp: &str) -> R<'_> {
     range_parse(
         inp,
@@ -269,7 +343,7 @@ fn parse_sha256(inp: &str) -> R<'_> {
                 || (i == 4 && c == '5')
                 || (i == 5 && c == '6')
                 || (i == 6 && c == ':')
-                || (i >= 7 && i < (7 + 64) && c.is_ascii_hexdigit())
+                || ((7..(7 + 64)).contains(&i) && c.is_ascii_hexdigit())
         },
     )
     .and_then(longer_than(7 + 63))
@@ -305,7 +379,7 @@ fn parse_ident_or_keyword(inp: &str) -> R<'_> {
         .and_then(longer_than(2))
     })
     .map(|mut tkn| {
-        const STRTOKS: &[(&str, fn(&str) -> Token)] = &[
+        const STRTOKS: &[(&str, ToToken)] = &[
             ("let", |s| Token::Let(s)),
             ("in", |s| Token::In(s)),
             ("with", |s| Token::With(s)),
@@ -314,6 +388,9 @@ fn parse_ident_or_keyword(inp: &str) -> R<'_> {
             ("else", |s| Token::Else(s)),
             ("forall", |s| Token::Forall(s)),
             ("merge", |s| Token::Merge(s)),
+            ("assert", |s| Token::Assert(s)),
+            ("toMap", |s| Token::ToMap(s)),
+            ("showConstructor", |s| Token::ShowConstructor(s)),
             ("missing", |s| Token::Missing(s)),
             ("as", |s| Token::As(s)),
         ];
@@ -401,7 +478,7 @@ fn parse_block_comment(inp: &str) -> R<'_> {
     .and_then(longer_than(4))
 }
 
-pub fn parse_dquot_raw_seg(inp: &str) -> R {
+pub fn parse_dquot_raw_seg(inp: &str) -> R<'_> {
     enum State {
         Init,
         Escape,
@@ -454,7 +531,7 @@ pub fn parse_dquot_raw_seg(inp: &str) -> R {
     )
 }
 
-pub fn parse_ddquote_raw_seg(inp: &str) -> R {
+pub fn parse_ddquote_raw_seg(inp: &str) -> R<'_> {
     enum State {
         Init,
         Dollar,
@@ -498,6 +575,14 @@ pub fn parse_ddquote_raw_seg(inp: &str) -> R {
                     state = State::Init;
                     3
                 }
+                (State::Esc2, '\'') => {
+                    state = State::Init;
+                    3
+                }
+                (State::Esc1, '$') => {
+                    state = State::Dollar;
+                    1
+                }
                 (State::Esc1, _) => {
                     state = State::Init;
                     2
@@ -518,11 +603,11 @@ where
 {
     let tkn = match inp {
         s if s.starts_with(opt1) => opt1,
-        s if opt2.len() > 0 && s.starts_with(opt2) => opt2,
+        s if !opt2.is_empty() && s.starts_with(opt2) => opt2,
         _ => return None,
     };
 
-    let len = tkn.as_bytes().len();
+    let len = tkn.len();
     let tkn = &inp[0..len];
     Some(to_token(tkn))
 }
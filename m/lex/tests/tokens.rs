@@ -0,0 +1,53 @@
+/// The tokens of `src`, as debug strings.
+fn tokens(src: &str) -> Vec<String> {
+    lex::Lex::new(src).map(|(_, t, _)| format!("{:?}", t)).collect()
+}
+
+fn first(src: &str) -> String {
+    tokens(src).remove(0)
+}
+
+#[test]
+fn naturals_and_integers() {
+    assert_eq!(tokens("1"), ["Natural(\"1\")"]);
+    assert_eq!(tokens("+12"), ["Natural(\"+12\")"]);
+    assert_eq!(tokens("-12"), ["Negative(\"-12\")"]);
+}
+
+#[test]
+fn doubles_need_a_fraction_or_an_exponent() {
+    assert_eq!(tokens("1.5"), ["Double(\"1.5\")"]);
+    assert_eq!(tokens("-2.5e-3"), ["Double(\"-2.5e-3\")"]);
+    assert_eq!(tokens("1e3"), ["Double(\"1e3\")"]);
+    assert_eq!(tokens("+1E+3"), ["Double(\"+1E+3\")"]);
+    assert_eq!(first("1."), "Natural(\"1\")");
+    assert_eq!(first("1e"), "Natural(\"1\")");
+    assert_eq!(first("1.e3"), "Natural(\"1\")");
+    assert_eq!(tokens("[1, 2.0]")[1..4], ["Natural(\"1\")", "Comma(\",\")", "Double(\"2.0\")"]);
+}
+
+#[test]
+fn special_doubles() {
+    assert_eq!(tokens("NaN"), ["Double(\"NaN\")"]);
+    assert_eq!(tokens("-Infinity"), ["Double(\"-Infinity\")"]);
+    assert_eq!(tokens("Infinity"), ["Double(\"Infinity\")"]);
+    assert_eq!(tokens("NaNs"), ["Ident(\"NaNs\")"]);
+}
+
+#[test]
+fn import_paths() {
+    assert_eq!(tokens("./a/b.dhall"), ["RelUri(\"./a/b.dhall\")"]);
+    assert_eq!(tokens("../a.dhall"), ["RelUri(\"../a.dhall\")"]);
+    assert_eq!(tokens("/etc/a.dhall"), ["AbsUri(\"/etc/a.dhall\")"]);
+    assert_eq!(tokens("~/a.dhall"), ["HomeUri(\"~/a.dhall\")"]);
+    assert_eq!(tokens("env:HOME"), ["EnvUri(\"env:HOME\")"]);
+    assert_eq!(tokens("https://a.b/c"), ["HttpUri(\"https://a.b/c\")"]);
+    assert_eq!(tokens("http://a.b/c"), ["HttpUri(\"http://a.b/c\")"]);
+}
+
+#[test]
+fn hashes() {
+    let hash = format!("sha256:{}", "0f".repeat(32));
+    assert_eq!(tokens(&hash), [format!("Sha256({:?})", hash)]);
+    assert_eq!(tokens(&format!("./a.dhall {}", hash))[1], format!("Sha256({:?})", hash));
+}
@@ -1,7 +1,14 @@
 pub const VERSION: &str = "0.0.1";
 
+/// An error raised by a grammar action, with the span of the source it
+/// was raised for.
 #[derive(Debug)]
-pub struct Error;
+pub struct Error {
+    pub message: String,
+    pub span: (usize, usize),
+}
+
+pub use ast::Spans;
 
 pub type ParseErrorE<'i, E> = lalrpop_util::ParseError<usize, ast::Token<'i>, E>;
 pub type ResultT<'i, T, E> = std::result::Result<T, ParseErrorE<'i, E>>;
@@ -12,4 +19,4 @@ pub type Result<'i, T> = ResultT<'i, T, Error>;
 #[macro_use]
 extern crate lalrpop_util;
 
-lalrpop_mod!(pub dhall);
+lalrpop_mod!(#[allow(clippy::all)] pub dhall);
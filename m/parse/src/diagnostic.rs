@@ -0,0 +1,129 @@
+use super::{ParseError, Spans};
+use ast::Location;
+use std::fmt;
+
+/// A parse error, rendered against the source it happened in.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub message: String,
+    pub location: Option<Location>,
+    /// The offending source line, the column and the width to underline.
+    pub snippet: Option<(String, usize, usize)>,
+    pub expected: Vec<String>,
+}
+
+impl Diagnostic {
+    /// Describe `err` without a source to point into.
+    pub fn new(err: &ParseError) -> Self {
+        let (message, _, expected) = describe(err);
+        Self {
+            message,
+            location: None,
+            snippet: None,
+            expected,
+        }
+    }
+
+    /// Describe `err`, pointing into the source of `spans`.
+    pub fn locate(spans: &Spans, err: &ParseError) -> Self {
+        let (message, (start, end), expected) = describe(err);
+        let location = spans.locate(ast::Span { start, end });
+
+        let text = spans.text;
+        let start = start.min(text.len());
+        let line_start = text[..start].rfind('\n').map(|n| n + 1).unwrap_or(0);
+        let line_end = text[start..].find('\n').map(|n| start + n).unwrap_or(text.len());
+        let line = &text[line_start..line_end];
+        let end = end.clamp(start, line_end);
+        let width = text[start..end].chars().count().max(1);
+
+        Self {
+            message,
+            snippet: Some((line.to_owned(), location.col, width)),
+            location: Some(location),
+            expected,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(location) = &self.location {
+            let gutter = " ".repeat(location.line.to_string().len());
+            write!(f, "\n{}--> {}", gutter, location)?;
+            if let Some((line, col, width)) = &self.snippet {
+                // Keep tabs so that the caret lines up with the source.
+                let indent: String = line
+                    .chars()
+                    .take(col - 1)
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect();
+                write!(f, "\n{} |", gutter)?;
+                write!(f, "\n{} | {}", location.line, line)?;
+                write!(f, "\n{} | {}{}", gutter, indent, "^".repeat(*width))?;
+            }
+        }
+        match self.expected.as_slice() {
+            [] => Ok(()),
+            [one] => write!(f, "\n  = expected {}", one),
+            many => write!(f, "\n  = expected one of {}", many.join(", ")),
+        }
+    }
+}
+
+fn describe(err: &ParseError) -> (String, (usize, usize), Vec<String>) {
+    match err {
+        ParseError::InvalidToken { location } => {
+            ("invalid token".to_owned(), (*location, *location), vec![])
+        }
+        ParseError::UnrecognizedEOF { location, expected } => (
+            "unexpected end of input".to_owned(),
+            (*location, *location),
+            expected_tokens(expected),
+        ),
+        ParseError::UnrecognizedToken {
+            token: (l, t, r),
+            expected,
+        } => (
+            format!("unexpected `{}`", t.as_str()),
+            (*l, *r),
+            expected_tokens(expected),
+        ),
+        ParseError::ExtraToken { token: (l, t, r) } => {
+            (format!("extra token `{}`", t.as_str()), (*l, *r), vec![])
+        }
+        ParseError::User { error } => (error.message.clone(), error.span, vec![]),
+    }
+}
+
+/// Translate lalrpop terminal names into dhall syntax.
+fn expected_tokens(expected: &[String]) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for name in expected {
+        let token = match name.as_str() {
+            "Ident" => "an identifier".to_owned(),
+            "Natural" => "a natural number".to_owned(),
+            "Negative" => "an integer".to_owned(),
+            "Double" => "a double".to_owned(),
+            "Bytes" => "a bytes literal".to_owned(),
+            "Text" | "RawText" => "text".to_owned(),
+            "RelUri" | "AbsUri" | "HomeUri" => "a path import".to_owned(),
+            "HttpUri" => "an http import".to_owned(),
+            "EnvUri" => "an env import".to_owned(),
+            "Sha256" => "a sha256 hash".to_owned(),
+            quoted => {
+                let literal = quoted
+                    .strip_prefix('"')
+                    .and_then(|q| q.strip_suffix('"'))
+                    .unwrap_or(quoted);
+                let literal = literal.replace("\\\"", "\"").replace("\\\\", "\\");
+                format!("`{}`", literal)
+            }
+        };
+        if !tokens.contains(&token) {
+            tokens.push(token);
+        }
+    }
+    tokens
+}
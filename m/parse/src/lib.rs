@@ -2,11 +2,21 @@ pub const VERSION: &str = "0.0.1";
 
 use std::io;
 
-pub use parse_lalrpop::{dhall, Error, ParseError, ParseErrorE, Result, ResultT};
+mod diagnostic;
+pub use diagnostic::Diagnostic;
 
-pub fn parse_str(inp: &str) -> Result<ast::Expr> {
+pub use parse_lalrpop::{dhall, Error, ParseError, ParseErrorE, Result, ResultT, Spans};
+
+pub fn parse_str(inp: &str) -> Result<'_, ast::Expr<'_>> {
+    let mut spans = Spans::default();
+    parse_spanned(&mut spans, inp)
+}
+
+/// Parse `inp`, recording the spans of its nodes into `spans`.
+pub fn parse_spanned<'i>(spans: &mut Spans<'i>, inp: &'i str) -> Result<'i, ast::Expr<'i>> {
+    spans.text = inp;
     let mut lex = lex::Lex::new(inp);
-    dhall::ExprParser::new().parse(&mut lex)
+    dhall::ExprParser::new().parse(spans, &mut lex)
 }
 
 pub fn parse_read<'i, R>(inp: &mut R, buf: &'i mut String) -> ResultT<'i, ast::Expr<'i>, io::Error>
@@ -15,6 +25,6 @@ where
 {
     inp.read_to_string(buf)?;
     let ast = parse_str(buf)
-        .map_err(|err| err.map_error(|_| io::Error::new(io::ErrorKind::Other, "oh no!")))?;
+        .map_err(|err| err.map_error(|e| io::Error::other(e.message)))?;
     Ok(ast)
 }
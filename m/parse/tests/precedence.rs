@@ -0,0 +1,41 @@
+use ast::{Expr, Term1};
+
+/// The operator at the root of `src`, with the ones at the roots of its operands.
+fn ops(src: &str) -> (String, String, String) {
+    let expr = parse::parse_str(src).unwrap();
+    let op = |t: &Term1| match t {
+        Term1::Operation(_, op, _) => op.to_string(),
+        _ => String::new(),
+    };
+    match expr {
+        Expr::Term1(Term1::Operation(a, root, b)) => (root.to_string(), op(&a), op(&b)),
+        e => panic!("not an operation: {:?}", e),
+    }
+}
+
+#[test]
+fn times_binds_tighter_than_plus() {
+    assert_eq!(ops("1 + 2 * 3"), ("+".into(), "".into(), "*".into()));
+    assert_eq!(ops("1 * 2 + 3"), ("+".into(), "*".into(), "".into()));
+}
+
+#[test]
+fn operators_associate_to_the_left() {
+    assert_eq!(ops("1 + 2 + 3"), ("+".into(), "+".into(), "".into()));
+}
+
+#[test]
+fn or_binds_looser_than_and() {
+    assert_eq!(ops("a || b && c"), ("||".into(), "".into(), "&&".into()));
+}
+
+#[test]
+fn equivalence_binds_loosest() {
+    assert_eq!(ops("1 + 1 ≡ 2"), ("≡".into(), "+".into(), "".into()));
+}
+
+#[test]
+fn minus_and_div_are_not_operators() {
+    assert!(parse::parse_str("a - b").is_err());
+    assert!(parse::parse_str("a / b").is_err());
+}
@@ -0,0 +1,17 @@
+/// `a` and `b` parse to the same tree.
+fn assert_same(a: &str, b: &str) {
+    let parsed = |src| format!("{:?}", parse::parse_str(src).unwrap());
+    assert_eq!(parsed(a), parsed(b), "{} and {}", a, b);
+}
+
+#[test]
+fn dotted_fields_are_nested_records() {
+    assert_same("{ a.b.c = 1 }", "{ a = { b = { c = 1 } } }");
+    assert_same("{ a.b = 1, a.c = 2 }", "{ a = { b = 1 } ∧ { c = 2 } }");
+}
+
+#[test]
+fn dotted_fields_nest_in_completions_and_handlers() {
+    assert_same("T::{ a.b = 1 }", "T::{ a = { b = 1 } }");
+    assert_same("merge { A.b = 1 } x", "merge { A = { b = 1 } } x");
+}
@@ -0,0 +1,42 @@
+use ast::{Expr, Term, Term1};
+
+/// The text of a literal without interpolations.
+fn text(src: &str) -> String {
+    match parse::parse_str(src).unwrap() {
+        Expr::Term1(Term1::Term(Term::Text(_, entries))) => entries.into_iter().map(|(c, _)| c.into_owned()).collect(),
+        e => panic!("not a text literal: {:?}", e),
+    }
+}
+
+fn error(src: &str) -> String {
+    let mut spans = parse::Spans::new("test.dhall", src);
+    let err = parse::parse_spanned(&mut spans, src).unwrap_err();
+    parse::Diagnostic::locate(&spans, &err).to_string()
+}
+
+#[test]
+fn escapes_are_decoded() {
+    assert_eq!(text(r#""a\"b\\c\/d\$e""#), "a\"b\\c/d$e");
+    assert_eq!(text(r#""\n\t\r\b\f""#), "\n\t\r\u{8}\u{c}");
+    assert_eq!(text(r#""é\u{1F600}😀""#), "é😀😀");
+}
+
+#[test]
+fn invalid_escapes_are_rejected() {
+    for src in [r#""\q""#, r#""\u{110000}""#, r#""\uZZZZ""#, r#""\uD800""#, r#""\u{}""#] {
+        assert!(parse::parse_str(src).is_err(), "{} parsed", src);
+    }
+}
+
+#[test]
+fn invalid_escapes_are_located() {
+    let err = error("{ x = \"a\\qb\" }");
+    assert!(err.starts_with("invalid escape sequence `\\qb`"), "{}", err);
+    assert!(err.contains("--> test.dhall:1:7"), "{}", err);
+}
+
+#[test]
+fn multi_line_literals_are_dedented() {
+    assert_eq!(text("''\n    a\n      b\n    ''"), "a\n  b\n");
+    assert_eq!(text("''\n  '''x ''${y}\n  ''"), "''x ${y}\n");
+}
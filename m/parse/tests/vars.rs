@@ -0,0 +1,15 @@
+use ast::{Expr, Term, Term1};
+
+#[test]
+fn indices_are_parsed() {
+    match parse::parse_str("x@65535").unwrap() {
+        Expr::Term1(Term1::Term(Term::Var("x", 65535))) => (),
+        e => panic!("not x@65535: {:?}", e),
+    }
+}
+
+#[test]
+fn overflowing_indices_are_parse_errors() {
+    let err = parse::parse_str("x@70000").unwrap_err();
+    assert!(format!("{:?}", err).contains("invalid variable index: x@70000"), "{:?}", err);
+}
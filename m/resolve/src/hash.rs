@@ -0,0 +1,478 @@
+use {
+    super::{Error, Result},
+    ast::{Expr, Term, Term1},
+    error::bail,
+    sha2::{Digest, Sha256},
+    std::convert::TryFrom,
+};
+
+/// The semantic hash of resolved `code`: the sha256 digest of the binary
+/// encoding of its alpha-beta-normal form.
+pub fn semantic_hash(name: &str, code: &str) -> Result<[u8; 32]> {
+    let mut spans = parse::Spans::new(name, code);
+    let mut ast = parse::parse_spanned(&mut spans, code).map_err(|e| Error::parse(&spans, e))?;
+    eval::TypeCheck::type_of(&ast, &mut eval::TypeCtx::new().with_spans(&spans))?;
+    let mut ctx = eval::ctx();
+    eval::eval(&mut ctx, &mut ast)?;
+
+    let mut encoder = Encoder::default();
+    encoder.expr(&ast)?;
+    let mut digest = [0; 32];
+    digest.copy_from_slice(&Sha256::digest(&encoder.out));
+    Ok(digest)
+}
+
+pub fn hex(digest: &[u8; 32]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Encode normal forms in the CBOR of the standard, with variables named
+/// `_`, as in alpha-normal form.
+#[derive(Default)]
+struct Encoder<'i> {
+    out: Vec<u8>,
+    /// The binders in scope, innermost last.
+    bound: Vec<&'i str>,
+}
+
+impl<'i> Encoder<'i> {
+    fn expr(&mut self, e: &Expr<'i>) -> Result<()> {
+        match e {
+            Expr::Term1(t1) => self.term1(t1),
+            Expr::Lambda(name, Some(typ), body) => {
+                self.array(3);
+                self.uint(1);
+                self.expr(typ)?;
+                self.under(name, body)
+            }
+            Expr::Lambda(_, None, _) => bail!("cannot hash unannotated {}", show::Show(e)),
+            Expr::Let(defs, body) => {
+                self.array(2 + 3 * defs.len());
+                self.uint(25);
+                let depth = self.bound.len();
+                for (name, typ, val) in defs {
+                    self.text("_");
+                    match typ {
+                        Some(typ) => self.expr(typ)?,
+                        None => self.null(),
+                    }
+                    self.expr(val)?;
+                    self.bound.push(unquote(name));
+                }
+                let body = self.expr(body);
+                self.bound.truncate(depth);
+                body
+            }
+        }
+    }
+
+    fn term1(&mut self, t1: &Term1<'i>) -> Result<()> {
+        match t1 {
+            Term1::Term(t) => self.term(t),
+            Term1::Evaluation(_, _) => {
+                let mut args = Vec::new();
+                let mut f = t1;
+                while let Term1::Evaluation(g, x) = peel(f) {
+                    args.push(x);
+                    f = g;
+                }
+                if let (Term1::Term(Term::Var("Some", 0)), [x]) = (peel(f), args.as_slice()) {
+                    if !self.bound.contains(&"Some") {
+                        self.array(3);
+                        self.uint(5);
+                        self.null();
+                        return self.term(x);
+                    }
+                }
+                self.array(2 + args.len());
+                self.uint(0);
+                self.term1(f)?;
+                args.iter().rev().try_for_each(|x| self.term(x))
+            }
+            Term1::Arrow(name, a, b) => {
+                self.array(3);
+                self.uint(2);
+                self.expr(a)?;
+                self.under(name.unwrap_or("_"), b)
+            }
+            Term1::With(t, path, val) => {
+                self.array(4);
+                self.uint(29);
+                self.term1(t)?;
+                self.array(path.len());
+                for &label in path {
+                    match label {
+                        "?" => self.uint(0),
+                        label => self.text(unquote(label)),
+                    }
+                }
+                self.term1(val)
+            }
+            Term1::Operation(a, op, b) => {
+                let code = match *op {
+                    "||" => 0,
+                    "&&" => 1,
+                    "==" => 2,
+                    "!=" => 3,
+                    "+" => 4,
+                    "*" => 5,
+                    "++" => 6,
+                    "#" => 7,
+                    "∧" | "/\\" => 8,
+                    "⩓" | "//\\\\" => 9,
+                    "⫽" | "//" => 10,
+                    "?" => 11,
+                    "≡" | "===" => 12,
+                    _ => bail!("cannot hash operator {}", op),
+                };
+                self.array(4);
+                self.uint(3);
+                self.uint(code);
+                self.term1(a)?;
+                self.term1(b)
+            }
+            Term1::IfThenElse(c, a, b) => {
+                self.array(4);
+                self.uint(14);
+                self.expr(c)?;
+                self.expr(a)?;
+                self.expr(b)
+            }
+            Term1::Assert(typ) => {
+                self.array(2);
+                self.uint(19);
+                self.expr(typ)
+            }
+            Term1::Ascribe(t, typ) => match peel(t) {
+                Term1::Term(Term::List(items)) if items.is_empty() => {
+                    self.array(2);
+                    match peel_expr(typ) {
+                        Some(Term1::Evaluation(list, item)) if matches!(peel(list), Term1::Term(Term::Var("List", 0))) => {
+                            self.uint(4);
+                            self.term(item)
+                        }
+                        _ => {
+                            self.uint(28);
+                            self.expr(typ)
+                        }
+                    }
+                }
+                Term1::Term(Term::Merge(handlers, u)) => {
+                    self.array(4);
+                    self.uint(6);
+                    self.record(8, handlers)?;
+                    self.term(u)?;
+                    self.expr(typ)
+                }
+                Term1::Term(Term::ToMap(u)) => {
+                    self.array(3);
+                    self.uint(27);
+                    self.term(u)?;
+                    self.expr(typ)
+                }
+                _ => {
+                    self.array(3);
+                    self.uint(26);
+                    self.term1(t)?;
+                    self.expr(typ)
+                }
+            },
+            Term1::Construct(t, fields) => {
+                self.array(4);
+                self.uint(3);
+                self.uint(13);
+                self.term1(t)?;
+                self.record(8, fields)
+            }
+            Term1::ImportAlt(_, _) => bail!("cannot hash unresolved {}", show::Show(t1)),
+        }
+    }
+
+    fn term(&mut self, t: &Term<'i>) -> Result<()> {
+        match t {
+            Term::Natural(n) => {
+                self.array(2);
+                self.uint(15);
+                self.natural(n);
+            }
+            Term::Integer(n) => {
+                self.array(2);
+                self.uint(16);
+                if n < &ast::BigInt::default() {
+                    let n = n.magnitude() - 1u8;
+                    match u64::try_from(&n) {
+                        Ok(n) => self.head(1, n),
+                        Err(_) => {
+                            self.head(6, 3);
+                            self.bytes(&n.to_bytes_be());
+                        }
+                    }
+                } else {
+                    self.natural(n.magnitude());
+                }
+            }
+            Term::Double(d) => self.double(*d),
+            Term::Bytes(bytes) => {
+                self.array(2);
+                self.uint(33);
+                self.bytes(bytes);
+            }
+            &Term::Var(name, idx) => self.var(unquote(name), idx),
+            Term::FieldAccess(t, name) => {
+                self.array(3);
+                self.uint(9);
+                self.term(t)?;
+                self.text(unquote(name));
+            }
+            Term::Project(1, t, selectors) => {
+                let mut labels = Vec::with_capacity(selectors.len());
+                for s in selectors {
+                    match s {
+                        Term1::Term(Term::Var(name, _)) => labels.push(unquote(name)),
+                        s => bail!("cannot hash selector {}", show::Show(s)),
+                    }
+                }
+                labels.sort_unstable();
+                self.array(2 + labels.len());
+                self.uint(10);
+                self.term(t)?;
+                labels.into_iter().for_each(|label| self.text(label));
+            }
+            Term::Project(_, t, selector) => {
+                self.array(3);
+                self.uint(10);
+                self.term(t)?;
+                self.array(selector.len());
+                selector.iter().try_for_each(|s| self.term1(s))?;
+            }
+            Term::Text(_, entries) => {
+                let holes = entries.iter().filter(|(_, hole)| hole.is_some()).count();
+                self.array(2 + 2 * holes);
+                self.uint(18);
+                let mut chunk = String::new();
+                for (text, hole) in entries {
+                    chunk.push_str(text);
+                    if let Some(hole) = hole {
+                        self.text(&chunk);
+                        self.expr(hole)?;
+                        chunk.clear();
+                    }
+                }
+                self.text(&chunk);
+            }
+            Term::List(items) if items.is_empty() => bail!("cannot hash unannotated []"),
+            Term::List(items) => {
+                self.array(2 + items.len());
+                self.uint(4);
+                self.null();
+                items.iter().try_for_each(|item| self.expr(item))?;
+            }
+            Term::Record(fields) => self.record(8, fields)?,
+            Term::TypeRecord(fields) => self.record(7, fields)?,
+            Term::TypeEnum(alts) => {
+                let mut alts: Vec<_> = alts.iter().map(|(name, typ)| (unquote(name), typ)).collect();
+                alts.sort_unstable_by_key(|&(name, _)| name);
+                self.array(2);
+                self.uint(11);
+                self.map(alts.len());
+                for (name, typ) in alts {
+                    self.text(name);
+                    match typ {
+                        Some(typ) => self.expr(typ)?,
+                        None => self.null(),
+                    }
+                }
+            }
+            Term::Expr(e) => self.expr(e)?,
+            Term::Merge(handlers, u) => {
+                self.array(3);
+                self.uint(6);
+                self.record(8, handlers)?;
+                self.term(u)?;
+            }
+            Term::ToMap(t) => {
+                self.array(2);
+                self.uint(27);
+                self.term(t)?;
+            }
+            Term::ShowConstructor(t) => {
+                self.array(2);
+                self.uint(34);
+                self.term(t)?;
+            }
+            Term::Path(_) | Term::Import(_) | Term::Embed(_) => bail!("cannot hash unresolved {}", show::Show(t)),
+        }
+        Ok(())
+    }
+
+    /// `body`, with a variable bound to `name`.
+    fn under(&mut self, name: &'i str, body: &Expr<'i>) -> Result<()> {
+        self.bound.push(unquote(name));
+        let body = self.expr(body);
+        self.bound.pop();
+        body
+    }
+
+    fn var(&mut self, name: &str, mut idx: u16) {
+        for (n, bound) in self.bound.iter().rev().enumerate() {
+            if *bound == name {
+                if idx == 0 {
+                    return self.uint(n as u64);
+                }
+                idx -= 1;
+            }
+        }
+        // Free variables of a closed expression are builtins.
+        match (name, idx) {
+            ("True", 0) => self.out.push(0xf5),
+            ("False", 0) => self.out.push(0xf4),
+            (name, 0) => self.text(name),
+            (name, idx) => {
+                self.array(2);
+                self.text(name);
+                self.uint(idx.into());
+            }
+        }
+    }
+
+    fn record(&mut self, kind: u64, fields: &ast::RecordData<'i>) -> Result<()> {
+        let mut entries = Vec::with_capacity(fields.len());
+        for (path, val) in fields {
+            match path.front() {
+                Some(name) if path.len() == 1 => entries.push((unquote(name), val)),
+                _ => bail!("cannot hash field {}", path.iter().copied().collect::<Vec<_>>().join(".")),
+            }
+        }
+        entries.sort_unstable_by_key(|&(name, _)| name);
+        self.array(2);
+        self.uint(kind);
+        self.map(entries.len());
+        for (name, val) in entries {
+            self.text(name);
+            self.expr(val)?;
+        }
+        Ok(())
+    }
+
+    fn natural(&mut self, n: &ast::BigUint) {
+        match u64::try_from(n) {
+            Ok(n) => self.uint(n),
+            Err(_) => {
+                self.head(6, 2);
+                self.bytes(&n.to_bytes_be());
+            }
+        }
+    }
+
+    /// The shortest of the half, single and double precision floats that
+    /// represents `d` exactly.
+    fn double(&mut self, d: f64) {
+        if d.is_nan() {
+            return self.out.extend([0xf9, 0x7e, 0x00]);
+        }
+        let single = d as f32;
+        if f64::from(single) != d {
+            self.out.push(0xfb);
+            return self.out.extend(d.to_bits().to_be_bytes());
+        }
+        match half(single) {
+            Some(half) => {
+                self.out.push(0xf9);
+                self.out.extend(half.to_be_bytes());
+            }
+            None => {
+                self.out.push(0xfa);
+                self.out.extend(single.to_bits().to_be_bytes());
+            }
+        }
+    }
+
+    fn head(&mut self, major: u8, n: u64) {
+        let major = major << 5;
+        match n {
+            0..=23 => self.out.push(major | n as u8),
+            24..=0xff => self.out.extend([major | 24, n as u8]),
+            0x100..=0xffff => {
+                self.out.push(major | 25);
+                self.out.extend((n as u16).to_be_bytes());
+            }
+            0x1_0000..=0xffff_ffff => {
+                self.out.push(major | 26);
+                self.out.extend((n as u32).to_be_bytes());
+            }
+            _ => {
+                self.out.push(major | 27);
+                self.out.extend(n.to_be_bytes());
+            }
+        }
+    }
+
+    fn uint(&mut self, n: u64) {
+        self.head(0, n)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.head(2, bytes.len() as u64);
+        self.out.extend(bytes);
+    }
+
+    fn text(&mut self, s: &str) {
+        self.head(3, s.len() as u64);
+        self.out.extend(s.as_bytes());
+    }
+
+    fn array(&mut self, len: usize) {
+        self.head(4, len as u64)
+    }
+
+    fn map(&mut self, len: usize) {
+        self.head(5, len as u64)
+    }
+
+    fn null(&mut self) {
+        self.out.push(0xf6)
+    }
+}
+
+/// The bits of `f` as a half precision float, if it is exactly one.
+fn half(f: f32) -> Option<u16> {
+    let bits = f.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    match exp {
+        0 if mantissa == 0 => Some(sign),
+        0xff => Some(sign | 0x7c00 | (mantissa >> 13) as u16),
+        // Normal
+        113..=142 if mantissa & 0x1fff == 0 => Some(sign | ((exp - 112) as u16) << 10 | (mantissa >> 13) as u16),
+        // Subnormal
+        102..=112 => {
+            let shift = 126 - exp;
+            let mantissa = mantissa | 0x80_0000;
+            (mantissa & ((1 << shift) - 1) == 0).then(|| sign | (mantissa >> shift) as u16)
+        }
+        _ => None,
+    }
+}
+
+/// `t` without the parentheses around it.
+fn peel<'a, 'i>(t: &'a Term1<'i>) -> &'a Term1<'i> {
+    match t {
+        Term1::Term(Term::Expr(e)) => peel_expr(e).unwrap_or(t),
+        t => t,
+    }
+}
+
+fn peel_expr<'a, 'i>(e: &'a Expr<'i>) -> Option<&'a Term1<'i>> {
+    match e {
+        Expr::Term1(t) => Some(peel(t)),
+        _ => None,
+    }
+}
+
+fn unquote(label: &str) -> &str {
+    label
+        .strip_prefix('`')
+        .and_then(|l| l.strip_suffix('`'))
+        .unwrap_or(label)
+}
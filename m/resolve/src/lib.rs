@@ -1,17 +1,20 @@
 pub const VERSION: &str = "0.0.1";
 
 use {
-    error::Result,
+    error::{Error, Result},
     std::{
-        borrow::Borrow,
+        borrow::{Borrow, Cow},
         collections::{hash_map::HashMap as Map, hash_set::HashSet as Set},
-        fmt, fs,
+        fmt::{self, Write},
+        fs, io,
         hash::Hash,
     },
 };
 
+mod hash;
 mod resolve;
-use resolve::Resolve;
+mod source;
+use {resolve::Resolve, source::SourceMap};
 
 pub struct Reservoir {
     // config
@@ -20,27 +23,92 @@ pub struct Reservoir {
     pub enable_fetch: bool,
     // across-state
     files: Map<String, (usize, String)>,
+    /// The names of the files each file embeds.
+    deps: Map<String, Vec<String>>,
+    /// The files imported from outside of any file.
+    roots: Vec<String>,
+    /// The text each loaded file was resolved from.
+    sources: Map<String, SourceMap>,
     pub fetched_uris: Set<String>,
     // iteration-state
-    uris: Set<String>,
+    /// The files being loaded, each importing the next.
+    chain: Vec<String>,
 }
 
 impl Reservoir {
     pub fn new(output_dir: String) -> Self {
         Self {
-            uris: <_>::default(),
+            chain: <_>::default(),
             fetched_uris: <_>::default(),
             files: <_>::default(),
+            deps: <_>::default(),
+            roots: <_>::default(),
+            sources: <_>::default(),
             enable_resolve: true,
             enable_fetch: false,
             output_dir,
         }
     }
 
+    /// The imported files, in the order they were loaded. Files only loaded
+    /// by an alternative of `?` that failed are left out.
     pub fn files(&self) -> Vec<(&String, &(usize, String))> {
-        let mut r: Vec<_> = self.files.iter().collect();
-        r.sort_by_key(|&(_, &(o, _))| o);
-        r
+        let roots: Vec<_> = self.roots.iter().map(String::as_str).collect();
+        self.reachable(&roots)
+            .into_iter()
+            .filter_map(|name| self.files.get_key_value(name))
+            .collect()
+    }
+
+    /// Locate `e`, an error of checking `code` as `Importer` writes it, in
+    /// the file it comes from.
+    pub fn relocate(&self, code: &str, e: Error) -> Error {
+        let location = match &e.location {
+            Some(location) => location,
+            None => return e,
+        };
+        let offset = ast::Spans::new("", code).offset(location.line, location.col);
+
+        let mut start = 0;
+        for (path, (_, tree)) in self.files() {
+            start += binding(path).len();
+            let end = start + tree.len();
+            if offset > end {
+                start = end + 1;
+                continue;
+            }
+            return match self.sources.get(path) {
+                Some(source) if offset >= start => {
+                    let from = source.offset(offset - start);
+                    let spans = ast::Spans::new(path, &source.text);
+                    let location = spans.locate(ast::Span { start: from, end: from });
+                    Error {
+                        location: Some(location),
+                        ..e
+                    }
+                }
+                _ => e,
+            };
+        }
+        e
+    }
+
+    /// The names of `roots` and of the files these depend on, in the order
+    /// they were loaded.
+    fn reachable<'s>(&'s self, roots: &[&'s str]) -> Vec<&'s str> {
+        let mut names = roots.to_vec();
+        let mut i = 0;
+        while i < names.len() {
+            for dep in self.deps.get(names[i]).into_iter().flatten() {
+                if !names.contains(&dep.as_str()) {
+                    names.push(dep);
+                }
+            }
+            i += 1;
+        }
+        names.retain(|n| self.files.contains_key(*n));
+        names.sort_by_key(|&n| self.files[n].0);
+        names
     }
 
     pub fn file<Q>(&self, id: &Q) -> Option<&str>
@@ -53,116 +121,226 @@ impl Reservoir {
 
     pub fn import_file<P: AsRef<str>>(&mut self, path: P) -> Result<()> {
         let path = path.as_ref();
+        self.add_root(path);
 
         if self.files.contains_key(path) {
             log::debug!("skip importing {}", path);
             return Ok(());
         }
+        self.check_cycle(path)?;
         log::debug!("importing {}", path);
 
-        let prefix = if is_http(path) { &self.output_dir } else { "" };
-        let real_path = format!("{}{}", prefix, path);
-
-        log::debug!("access: {}", real_path);
-        let mut file = fs::File::open(&real_path)?;
-
         let mut read_buffer = String::new();
-        read_buffer.clear();
-        let mut ast = parse::parse_read(&mut file, &mut read_buffer)?;
-
-        let tree = if self.enable_resolve {
-            self.uris.clear();
-            let base_path = path;
-            let mut path = String::new();
+        io::Read::read_to_string(&mut self.open(path)?, &mut read_buffer)?;
+        self.load(path, read_buffer)
+    }
 
-            if self.enable_fetch {
-                ast.visit_register(|p| {
-                    path.push_str(p);
-                    path_resolve(&base_path, &mut path);
-                    log::trace!("[register] resolved as {}", path);
+    /// Import the code in the environment variable `name`, as `path`.
+    pub fn import_env(&mut self, path: &str, name: &str) -> Result<()> {
+        self.add_root(path);
 
-                    if is_http(&path) {
-                        log::trace!("register url [{:02}]", self.uris.len());
-                        self.uris.insert(path.clone());
-                    }
+        if self.files.contains_key(path) {
+            log::debug!("skip importing {}", path);
+            return Ok(());
+        }
+        self.check_cycle(path)?;
+        log::debug!("importing {}", path);
 
-                    path.clear();
-                    Ok(())
-                })?;
+        let read_buffer = std::env::var(name)?;
+        self.load(path, read_buffer)
+    }
 
-                self.fetch_http()?;
+    /// Import `path` as a Text or Bytes literal, named `name`. Its contents
+    /// are not parsed.
+    fn import_raw(&mut self, name: &str, path: &str, target: &ast::ImportTarget, mode: ast::ImportMode) -> Result<()> {
+        if self.files.contains_key(name) {
+            log::debug!("skip importing {}", name);
+            return Ok(());
+        }
+        log::debug!("importing {}", name);
+
+        let contents = match target {
+            ast::ImportTarget::Env(var) => std::env::var(var.as_ref())?.into_bytes(),
+            _ => {
+                let mut contents = Vec::new();
+                io::Read::read_to_end(&mut self.open(path)?, &mut contents)?;
+                contents
             }
+        };
+        let literal = match mode {
+            ast::ImportMode::Bytes => ast::Term::Bytes(contents),
+            _ => {
+                let text = String::from_utf8(contents).map_err(|e| Error::import(format!("{} as Text: {}", path, e)))?;
+                ast::Term::Text(1, ast::deq((Cow::Owned(text), None)))
+            }
+        };
+        self.insert(name, format!("{}", show::Show(&literal)));
+        Ok(())
+    }
 
-            ast.visit_import(|p, t| {
-                path.push_str(p);
-                path_resolve(&base_path, &mut path);
-                log::trace!("[import] resolved as {}", path);
-
-                self.import_file(path.clone())?;
-
-                *t = ast::Term::Embed(format!("`{}`", path));
+    fn add_root(&mut self, path: &str) {
+        if self.chain.is_empty() && !self.roots.iter().any(|r| r == path) {
+            self.roots.push(path.to_owned());
+        }
+    }
 
-                path.clear();
-                Ok(())
-            })?;
+    /// Fail to import `path` while it is being loaded, further up the chain.
+    fn check_cycle(&self, path: &str) -> Result<()> {
+        match self.chain.iter().position(|p| p == path) {
+            Some(start) => {
+                let cycle = self.chain[start..].join(" -> ");
+                Err(Error::import(format!("cyclic import: {} -> {}", cycle, path)))
+            }
+            None => Ok(()),
+        }
+    }
 
-            format!("{}", show::Show(&ast))
-        } else {
-            read_buffer
+    fn open(&self, path: &str) -> Result<fs::File> {
+        let real_path = match path.strip_prefix('~') {
+            _ if is_http(path) => format!("{}{}", self.output_dir, path),
+            Some(rest) => format!("{}{}", std::env::var("HOME")?.trim_end_matches('/'), rest),
+            None => path.to_owned(),
         };
 
+        log::debug!("access: {}", real_path);
+        Ok(fs::File::open(&real_path)?)
+    }
+
+    fn insert(&mut self, path: &str, tree: String) {
         let order = self.files.len();
         self.files.insert(path.to_owned(), (order, tree));
+    }
+
+    /// The code of `name`, with the files it depends on bound by `let`s.
+    fn resolved(&self, name: &str) -> String {
+        let mut code = String::new();
+        for n in self.reachable(&[name]) {
+            let _ = writeln!(code, "let `{}` = {}", n, self.files[n].1);
+        }
+        let _ = write!(code, "in `{}`", name);
+        code
+    }
+
+    /// Parse the code read for `path` and resolve its imports.
+    fn load(&mut self, path: &str, read_buffer: String) -> Result<()> {
+        self.chain.push(path.to_owned());
+        let resolved = self.resolve_code(path, &read_buffer);
+        self.chain.pop();
+
+        let (tree, source) = resolved?;
+        self.sources.insert(path.to_owned(), source);
+        self.insert(path, tree);
         Ok(())
     }
 
-    fn fetch_http(&mut self) -> Result<()> {
-        let opts = [
-            "--no-progress-meter",
-            "--create-dirs",
-            "--output-dir",
-            &self.output_dir,
-        ];
-
-        let mut args: Vec<String> = opts.iter().map(|&s| s.to_owned()).collect();
-        let mut fetch_count = self.fetched_uris.len();
-        for uri in &self.uris {
-            if is_http(uri) && !self.fetched_uris.contains(uri) {
-                args.push(uri.clone());
-                args.push(format!("--output"));
-                args.push(uri.clone());
-                self.fetched_uris.insert(uri.clone());
+    /// The code of `path`, with its imports replaced by the names of what
+    /// they resolve to.
+    fn resolve_code(&mut self, path: &str, read_buffer: &str) -> Result<(String, SourceMap)> {
+        let mut spans = parse::Spans::new(path, read_buffer);
+        let mut ast = parse::parse_spanned(&mut spans, read_buffer)
+            .map_err(|e| Error::parse(&spans, e))?;
+
+        let mut edits = Vec::new();
+        if self.enable_resolve {
+            let base_path = path;
+            let mut path = String::new();
+
+            let choices = ast.visit_import(|t| {
+                let import = match t {
+                    ast::Term::Import(import) => import,
+                    _ => return Ok(()),
+                };
+                path.clear();
+                path.push_str(&show::Show(&import.target).to_string());
+                path_resolve(base_path, &mut path);
+                log::trace!("[import] resolved as {}", path);
+
+                let span = spans.get(import.as_ref());
+                let at = span.map(|s| spans.locate(s));
+                if import.mode == ast::ImportMode::Location {
+                    let location = location(&path, &import.target).and_then(|location| {
+                        if let Some(expected) = &import.hash {
+                            let name = format!("{} as Location", path);
+                            verify(&name, &show::Show(&location).to_string(), expected)?;
+                        }
+                        Ok(location)
+                    });
+                    *t = location.map_err(|e| e.at(at))?;
+                    edits.extend(span.map(|s| (s, format!("({})", show::Show(&*t)))));
+                    return Ok(());
+                }
+                if import.target == ast::ImportTarget::Missing {
+                    return Err(Error::import("missing").at(at));
+                }
+                // Referential sanity: remote code may only import remote code.
+                if is_http(base_path) && !is_http(&path) {
+                    let e = Error::import(format!("remote {} cannot import {}", base_path, path));
+                    return Err(e.at(at));
+                }
+                let name = match import.mode {
+                    ast::ImportMode::Code => path.clone(),
+                    mode => format!("{} as {:?}", path, mode),
+                };
+                let fetched = if is_http(&path) { self.fetch(&path) } else { Ok(()) };
+                let imported = fetched.and_then(|()| match (import.mode, &import.target) {
+                    (ast::ImportMode::Code, ast::ImportTarget::Env(var)) => self.import_env(&path, var),
+                    (ast::ImportMode::Code, _) => self.import_file(&path),
+                    (mode, target) => self.import_raw(&name, &path, target, mode),
+                });
+                let verified = imported.and_then(|()| match &import.hash {
+                    Some(expected) => verify(&name, &self.resolved(&name), expected),
+                    None => Ok(()),
+                });
+                verified.map_err(|e| e.at(at))?;
+
+                *t = ast::Term::Embed(format!("`{}`", name));
+                edits.extend(span.map(|s| (s, format!("`{}`", name))));
+                Ok(())
+            })?;
+
+            // Drop the alternatives not chosen, along with the `?`.
+            for (a, b, first) in choices {
+                if let (Some(a), Some(b)) = (spans.of_id(a), spans.of_id(b)) {
+                    let dropped = match first {
+                        true => ast::Span { start: a.end, end: b.end },
+                        false => ast::Span { start: a.start, end: b.start },
+                    };
+                    edits.push((dropped, String::new()));
+                }
             }
+
+            let mut deps = Vec::new();
+            ast.visit_embed(|name| {
+                deps.push(name.trim_matches('`').to_owned());
+                Ok(())
+            })?;
+            self.deps.insert(base_path.to_owned(), deps);
         }
-        fetch_count = self.fetched_uris.len() - fetch_count;
+        Ok(SourceMap::splice(read_buffer, edits))
+    }
 
-        if fetch_count == 0 {
+    /// Download `uri` into `output_dir`, unless it has been already.
+    fn fetch(&mut self, uri: &str) -> Result<()> {
+        if !self.enable_fetch || self.fetched_uris.contains(uri) {
             return Ok(());
         }
 
         use std::process::{Command, Stdio};
 
         let mut cmd = Command::new("curl");
-
         cmd.stdin(Stdio::piped());
-        cmd.args(args);
+        cmd.args(["--no-progress-meter", "--fail", "--create-dirs", "--output-dir", &self.output_dir]);
+        cmd.args([uri, "--output", uri]);
 
-        log::debug!(
-            "Curling: {:?} + {} uris into {}",
-            opts,
-            fetch_count,
-            &self.output_dir
-        );
-
-        let mut proc = cmd.spawn()?;
-        let status = proc.wait()?;
+        log::debug!("Curling: {} into {}", uri, &self.output_dir);
 
+        let status = cmd.spawn()?.wait()?;
         if !status.success() {
-            return Err(format!("curl failed: {:?}", status).into());
+            return Err(Error::import(format!("fetching {} failed: {}", uri, status)));
         }
 
-        log::info!("Curled: {} uris into {}", fetch_count, &self.output_dir);
-
+        log::info!("Curled: {} into {}", uri, &self.output_dir);
+        self.fetched_uris.insert(uri.to_owned());
         Ok(())
     }
 }
@@ -174,8 +352,63 @@ fn is_http(path: &str) -> bool {
     path.starts_with(HTTP) || path.starts_with(HTTPS)
 }
 
+const ENV: &str = "env:";
+
 fn is_absolute(path: &str) -> bool {
-    is_http(path) || path.starts_with("/")
+    is_http(path) || path.starts_with('/') || path.starts_with("~/") || path.starts_with(ENV)
+}
+
+/// Check that the semantic hash of the resolved `code` of `name` is `expected`.
+fn verify(name: &str, code: &str, expected: &[u8; 32]) -> Result<()> {
+    let actual = hash::semantic_hash(name, code)?;
+    if actual != *expected {
+        let (expected, actual) = (hash::hex(expected), hash::hex(&actual));
+        let msg = format!("hash mismatch for {}: expected sha256:{}, found sha256:{}", name, expected, actual);
+        return Err(Error::import(msg));
+    }
+    Ok(())
+}
+
+/// The `< Local | Remote | Environment | Missing >` value of an import
+/// `as Location`, resolved as `path`.
+fn location<'i>(path: &str, target: &ast::ImportTarget) -> Result<ast::Term<'i>> {
+    use ast::{FilePrefix, ImportTarget::*, Term, Term1};
+
+    let (alternative, value) = match target {
+        Local(FilePrefix::Home, _) | Remote(_) if is_http(path) || path.starts_with('~') => {
+            (if is_http(path) { "Remote" } else { "Local" }, Some(path.to_owned()))
+        }
+        Local(_, _) => {
+            let mut absolute = match path {
+                p if p.starts_with('/') => p.to_owned(),
+                p => format!("{}/{}", std::env::current_dir()?.display(), p),
+            };
+            path_clean(&mut absolute);
+            ("Local", Some(absolute))
+        }
+        Remote(_) => ("Remote", Some(path.to_owned())),
+        Env(name) => ("Environment", Some(name.to_string())),
+        Missing => ("Missing", None),
+    };
+
+    let text = || Some(Box::new(ast::var_expr("Text")));
+    let union = Term::TypeEnum(
+        vec![
+            ("Local", text()),
+            ("Remote", text()),
+            ("Environment", text()),
+            ("Missing", None),
+        ]
+        .into(),
+    );
+    let constructor = Term::FieldAccess(Box::new(union), alternative);
+    Ok(match value {
+        Some(value) => {
+            let value = Term::Text(1, ast::deq((Cow::Owned(value), None)));
+            ast::Expr::from(Term1::Evaluation(Box::new(Term1::Term(constructor)), value)).into()
+        }
+        None => constructor,
+    })
 }
 
 fn dir_base(path: &str) -> (&str, &str) {
@@ -183,7 +416,8 @@ fn dir_base(path: &str) -> (&str, &str) {
 }
 
 fn path_resolve(base: &str, path: &mut String) {
-    if !is_absolute(path) {
+    // Paths imported by environment variables are relative to the working directory.
+    if !is_absolute(path) && !base.starts_with(ENV) {
         let (dir, _) = dir_base(base);
         path.insert_str(0, dir);
         path_clean(path);
@@ -191,15 +425,11 @@ fn path_resolve(base: &str, path: &mut String) {
 }
 
 fn path_clean(path: &mut String) {
-    loop {
-        if let Some(n) = path.find("././") {
-            path.remove(n + 3);
-            path.remove(n + 2);
-            path.remove(n + 1);
-            path.remove(n + 0);
-        } else {
-            break;
-        }
+    while let Some(n) = path.find("././") {
+        path.remove(n + 3);
+        path.remove(n + 2);
+        path.remove(n + 1);
+        path.remove(n);
     }
 
     let mut trace = Vec::new();
@@ -216,7 +446,7 @@ fn path_clean(path: &mut String) {
             q = trace.pop().unwrap_or(0);
         } else if path[p..].starts_with("./") && p > 0 {
             path.remove(p + 1);
-            path.remove(p + 0);
+            path.remove(p);
         } else if let Some(n) = path[p..].find('/') {
             trace.push(q);
             q = p;
@@ -227,12 +457,17 @@ fn path_clean(path: &mut String) {
     }
 }
 
+/// The start of the `let` binding an imported file to its name.
+fn binding(path: &str) -> String {
+    format!("let `{}` = ", path)
+}
+
 pub struct Importer<'r>(pub &'r mut Reservoir);
 impl<'r> fmt::Display for Importer<'r> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Self(r) = self;
         for (path, (_, code)) in r.files() {
-            writeln!(f, "let `{}` = {}", path, code)?;
+            writeln!(f, "{}{}", binding(path), code)?;
         }
         Ok(())
     }
@@ -1,31 +1,50 @@
 use super::*;
 
 pub trait Visitor {
-    fn visit_import(&mut self, path: &str, term: &mut ast::Term) -> Result<()> {
-        let _ = (path, term);
+    /// Visit an import term, to be replaced by what it resolves to.
+    fn visit_import(&mut self, term: &mut ast::Term) -> Result<()> {
+        let _ = term;
         Ok(())
     }
-    fn visit_register(&mut self, path: &str) -> Result<()> {
-        let _ = path;
+    /// Visit the name of resolved code embedded in place of an import.
+    fn visit_embed(&mut self, name: &str) -> Result<()> {
+        let _ = name;
         Ok(())
     }
+    /// Whether `a ? b` is replaced by the first alternative that resolves,
+    /// rather than having both visited.
+    fn chooses_alternative(&self) -> bool {
+        false
+    }
+    /// Visit the choice of `a` (`first`) or `b` to replace `a ? b` with.
+    fn visit_choice(&mut self, a: &ast::Term1, b: &ast::Term1, first: bool) {
+        let _ = (a, b, first);
+    }
 }
 
+/// The alternative chosen for an `a ? b`: the nodes of `a` and `b`, and
+/// whether it was `a`.
+pub type Choice = (ast::NodeId, ast::NodeId, bool);
+
 pub trait Resolve {
     fn resolve<V: Visitor>(&mut self, reservoir: &mut V) -> Result<()>;
 
-    fn visit_import<F>(&mut self, f: F) -> Result<()>
+    /// Visit the imports, choosing between alternatives. The choices made
+    /// are returned.
+    fn visit_import<F>(&mut self, f: F) -> Result<Vec<Choice>>
     where
-        F: FnMut(&str, &mut ast::Term) -> Result<()>,
+        F: FnMut(&mut ast::Term) -> Result<()>,
     {
-        self.resolve(&mut ImportVisitor(f))
+        let mut visitor = ImportVisitor(f, Vec::new());
+        self.resolve(&mut visitor)?;
+        Ok(visitor.1)
     }
 
-    fn visit_register<F>(&mut self, f: F) -> Result<()>
+    fn visit_embed<F>(&mut self, f: F) -> Result<()>
     where
         F: FnMut(&str) -> Result<()>,
     {
-        self.resolve(&mut RegisterVisitor(f))
+        self.resolve(&mut EmbedVisitor(f))
     }
 }
 
@@ -50,8 +69,27 @@ impl<'i> Resolve for ast::Term1<'i> {
             With(t, _, v) => (t, v).resolve(r),
             Operation(a, _, b) => (a, b).resolve(r),
             IfThenElse(c, a, b) => (c, a, b).resolve(r),
+            Assert(t) => t.resolve(r),
             Ascribe(t, v) => (t, v).resolve(r),
             Construct(t, d) => (t, d).resolve(r),
+            ImportAlt(a, b) if r.chooses_alternative() => {
+                let chosen = match a.resolve(r) {
+                    Ok(()) => {
+                        r.visit_choice(a, b, true);
+                        a
+                    }
+                    Err(e) if e.is_import_failure() => {
+                        log::debug!("falling back: {}", e);
+                        b.resolve(r)?;
+                        r.visit_choice(a, b, false);
+                        b
+                    }
+                    Err(e) => return Err(e),
+                };
+                *self = std::mem::take(chosen.as_mut());
+                Ok(())
+            }
+            ImportAlt(a, b) => (a, b).resolve(r),
         }
     }
 }
@@ -60,8 +98,8 @@ impl<'i> Resolve for ast::Term<'i> {
     fn resolve<V: Visitor>(&mut self, r: &mut V) -> Result<()> {
         use ast::Term::*;
         match self {
-            Integer(_) => Ok(()),
-            Double(_) => Ok(()),
+            Natural(_) | Integer(_) => Ok(()),
+            Double(_) | Bytes(_) => Ok(()),
             FieldAccess(term, _) => term.resolve(r),
             Project(_, term, fields) => (term, fields).resolve(r),
             Path(_) => Ok(()),
@@ -70,17 +108,11 @@ impl<'i> Resolve for ast::Term<'i> {
             List(vs) => vs.resolve(r),
             TypeRecord(es) | Record(es) => es.resolve(r),
             TypeEnum(es) => es.resolve(r),
-            Import {
-                path: "missing", ..
-            } => Ok(()),
-            &mut Import { path, .. } => {
-                r.visit_register(path)?;
-                r.visit_import(path, self)?;
-                Ok(())
-            }
-            Embed(_) => Ok(()),
+            Import(_) => r.visit_import(self),
+            Embed(name) => r.visit_embed(name),
             Expr(e) => e.resolve(r),
             Merge(d, t) => (d, t).resolve(r),
+            ToMap(t) | ShowConstructor(t) => t.resolve(r),
         }
     }
 }
@@ -126,34 +158,46 @@ impl<T: Resolve> Resolve for ast::Deq<T> {
     }
 }
 
-impl<'a, T: Resolve> Resolve for &'a mut T {
+impl<T: Resolve> Resolve for &mut T {
     fn resolve<V: Visitor>(&mut self, r: &mut V) -> Result<()> {
         T::resolve(self, r)
     }
 }
 
-impl<'a> Resolve for &'a str {
+impl Resolve for &str {
     fn resolve<V: Visitor>(&mut self, _: &mut V) -> Result<()> {
         Ok(())
     }
 }
 
-struct ImportVisitor<F>(F);
+impl Resolve for std::borrow::Cow<'_, str> {
+    fn resolve<V: Visitor>(&mut self, _: &mut V) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct ImportVisitor<F>(F, Vec<Choice>);
 impl<F> Visitor for ImportVisitor<F>
 where
-    F: FnMut(&str, &mut ast::Term) -> Result<()>,
+    F: FnMut(&mut ast::Term) -> Result<()>,
 {
-    fn visit_import(&mut self, path: &str, term: &mut ast::Term) -> Result<()> {
-        (self.0)(path, term)
+    fn visit_import(&mut self, term: &mut ast::Term) -> Result<()> {
+        (self.0)(term)
+    }
+    fn chooses_alternative(&self) -> bool {
+        true
+    }
+    fn visit_choice(&mut self, a: &ast::Term1, b: &ast::Term1, first: bool) {
+        self.1.push((ast::node_id(a), ast::node_id(b), first));
     }
 }
 
-struct RegisterVisitor<F>(F);
-impl<F> Visitor for RegisterVisitor<F>
+struct EmbedVisitor<F>(F);
+impl<F> Visitor for EmbedVisitor<F>
 where
     F: FnMut(&str) -> Result<()>,
 {
-    fn visit_register(&mut self, path: &str) -> Result<()> {
-        (self.0)(path)
+    fn visit_embed(&mut self, name: &str) -> Result<()> {
+        (self.0)(name)
     }
 }
@@ -0,0 +1,58 @@
+use ast::Span;
+
+/// Code resolved from the text of a file, by replacing spans of it, mapped
+/// back to the text.
+pub struct SourceMap {
+    pub text: String,
+    segments: Vec<Segment>,
+}
+
+/// A piece of the code copied from the text: `len` bytes at `at` in the
+/// code, from `from` in the text.
+struct Segment {
+    at: usize,
+    from: usize,
+    len: usize,
+}
+
+impl SourceMap {
+    /// Replace the spans of `text` that `edits` give. Edits inside a span
+    /// replaced already are dropped.
+    pub fn splice(text: &str, mut edits: Vec<(Span, String)>) -> (String, Self) {
+        edits.sort_by_key(|(span, _)| (span.start, std::cmp::Reverse(span.end)));
+
+        let mut code = String::with_capacity(text.len());
+        let mut segments = Vec::with_capacity(edits.len() + 1);
+        let mut cursor = 0;
+        for (span, replacement) in edits {
+            if span.start < cursor {
+                continue;
+            }
+            segments.push(Segment {
+                at: code.len(),
+                from: cursor,
+                len: span.start - cursor,
+            });
+            code.push_str(&text[cursor..span.start]);
+            code.push_str(&replacement);
+            cursor = span.end;
+        }
+        segments.push(Segment {
+            at: code.len(),
+            from: cursor,
+            len: text.len() - cursor,
+        });
+        code.push_str(&text[cursor..]);
+
+        let text = text.to_owned();
+        (code, Self { text, segments })
+    }
+
+    /// The offset in the text of `offset` in the code. Offsets into a
+    /// replacement map to the start of the span it replaced.
+    pub fn offset(&self, offset: usize) -> usize {
+        let i = self.segments.partition_point(|s| s.at <= offset).saturating_sub(1);
+        let s = &self.segments[i];
+        s.from + (offset - s.at).min(s.len)
+    }
+}
@@ -0,0 +1,52 @@
+mod common;
+use common::Dir;
+
+#[test]
+fn first_alternative_that_resolves_is_chosen() {
+    let dir = Dir::new(
+        "alt-first",
+        &[("root.dhall", "./none.dhall ? ./one.dhall ? 2"), ("one.dhall", "1")],
+    );
+    assert_eq!(dir.normalize("root.dhall"), Ok("1".to_owned()));
+}
+
+#[test]
+fn missing_falls_back() {
+    let dir = Dir::new("alt-missing", &[("root.dhall", "missing ? env:DUST_TEST_UNSET ? 3")]);
+    assert_eq!(dir.normalize("root.dhall"), Ok("3".to_owned()));
+}
+
+#[test]
+fn invalid_code_does_not_fall_back() {
+    let dir = Dir::new("alt-parse", &[("root.dhall", "./bad.dhall ? 1"), ("bad.dhall", "1 +")]);
+    let err = dir.resolve("root.dhall").unwrap_err();
+    assert!(err.contains("parse"), "{}", err);
+}
+
+#[test]
+fn failed_alternatives_leave_no_files_behind() {
+    let dir = Dir::new(
+        "alt-rollback",
+        &[
+            ("root.dhall", "./x.dhall ? 1"),
+            ("x.dhall", "[ ./bad.dhall, missing ]"),
+            ("bad.dhall", "1 + True"),
+        ],
+    );
+    assert_eq!(dir.normalize("root.dhall"), Ok("1".to_owned()));
+    assert!(!dir.resolve("root.dhall").unwrap().contains("bad.dhall"));
+}
+
+#[test]
+fn failed_fetches_fall_back() {
+    let dir = Dir::new("alt-fetch", &[("root.dhall", "https://unreachable.invalid/x.dhall ? 1")]);
+    let code = dir.resolve_with("root.dhall", |r| r.enable_fetch = true).unwrap();
+    assert_eq!(dir.normalize_code(&code), Ok("1".to_owned()));
+}
+
+#[test]
+fn unused_alternatives_are_not_fetched() {
+    let dir = Dir::new("alt-lazy", &[("root.dhall", "1 ? https://unreachable.invalid/x.dhall")]);
+    let code = dir.resolve_with("root.dhall", |r| r.enable_fetch = true).unwrap();
+    assert_eq!(dir.normalize_code(&code), Ok("1".to_owned()));
+}
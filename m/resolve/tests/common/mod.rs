@@ -0,0 +1,83 @@
+// Each test uses only some of the helpers.
+#![allow(dead_code)]
+
+use std::{fs, path::PathBuf};
+
+/// A temporary directory of dhall files, removed on drop.
+pub struct Dir(PathBuf);
+
+impl Dir {
+    pub fn new(name: &str, files: &[(&str, &str)]) -> Self {
+        let dir = std::env::temp_dir().join(format!("dust-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        for (file, code) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, code).unwrap();
+        }
+        Self(dir)
+    }
+
+    /// The path of `file` in the directory.
+    pub fn path(&self, file: &str) -> String {
+        self.0.join(file).display().to_string()
+    }
+
+    /// Resolve `file` into the code the cli checks: every imported file
+    /// bound by a `let`.
+    pub fn resolve(&self, file: &str) -> Result<String, String> {
+        self.resolve_with(file, |_| ())
+    }
+
+    /// Resolve `file`, with the reservoir configured by `config`.
+    pub fn resolve_with<F>(&self, file: &str, config: F) -> Result<String, String>
+    where
+        F: FnOnce(&mut resolve::Reservoir),
+    {
+        let path = self.path(file);
+        let mut r = resolve::Reservoir::new(self.path("cache/"));
+        config(&mut r);
+        r.import_file(&path).map_err(|e| e.to_string())?;
+        Ok(format!("{} in `{}`", resolve::Importer(&mut r), path))
+    }
+
+    /// The normal form of `file`, resolved, type checked and evaluated.
+    pub fn normalize(&self, file: &str) -> Result<String, String> {
+        self.normalize_code(&self.resolve(file)?)
+    }
+
+    /// The normal form of resolved `code`.
+    pub fn normalize_code(&self, code: &str) -> Result<String, String> {
+        let mut expr = parse::parse_str(code).map_err(|e| error::Error::from(e).to_string())?;
+        eval::typecheck(&expr).map_err(|e| e.to_string())?;
+        let mut ctx = eval::ctx();
+        eval::eval(&mut ctx, &mut expr).map_err(|e| e.to_string())?;
+        Ok(eval::Show(&expr).to_string().trim().to_owned())
+    }
+}
+
+impl Dir {
+    /// The error checking `file` the way the cli does, located in the file
+    /// it comes from.
+    pub fn check_error(&self, file: &str) -> String {
+        let path = self.path(file);
+        let mut r = resolve::Reservoir::new(self.path("cache/"));
+        r.import_file(&path).unwrap();
+        let code = format!("{} in `{}`", resolve::Importer(&mut r), path);
+
+        let mut spans = parse::Spans::new("(resolved)", &code);
+        let mut expr = parse::parse_spanned(&mut spans, &code).unwrap();
+        let mut ctx = eval::TypeCtx::new().with_spans(&spans);
+        let err = match eval::TypeCheck::type_of(&expr, &mut ctx) {
+            Err(e) => e,
+            Ok(_) => eval::eval(&mut eval::ctx().with_spans(&spans), &mut expr).err().unwrap(),
+        };
+        r.relocate(&code, err).to_string()
+    }
+}
+
+impl Drop for Dir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
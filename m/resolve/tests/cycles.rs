@@ -0,0 +1,40 @@
+mod common;
+use common::Dir;
+
+#[test]
+fn cyclic_imports_fail() {
+    let dir = Dir::new("cycle", &[("a.dhall", "./b.dhall"), ("b.dhall", "./a.dhall")]);
+    let err = dir.resolve("a.dhall").unwrap_err();
+    let (a, b) = (dir.path("a.dhall"), dir.path("b.dhall"));
+    assert!(err.contains(&format!("cyclic import: {} -> {} -> {}", a, b, a)), "{}", err);
+}
+
+#[test]
+fn self_imports_fail() {
+    let dir = Dir::new("self", &[("a.dhall", "{ a = ./a.dhall }")]);
+    let err = dir.resolve("a.dhall").unwrap_err();
+    assert!(err.contains("cyclic import"), "{}", err);
+}
+
+#[test]
+fn cyclic_imports_fall_back() {
+    let dir = Dir::new(
+        "cycle-alt",
+        &[("root.dhall", "./a.dhall ? 1"), ("a.dhall", "./b.dhall"), ("b.dhall", "./a.dhall + 1")],
+    );
+    assert_eq!(dir.normalize("root.dhall"), Ok("1".to_owned()));
+}
+
+#[test]
+fn diamonds_are_not_cycles() {
+    let dir = Dir::new(
+        "diamond",
+        &[
+            ("root.dhall", "./a.dhall + ./b.dhall"),
+            ("a.dhall", "./c.dhall"),
+            ("b.dhall", "./c.dhall * 2"),
+            ("c.dhall", "1"),
+        ],
+    );
+    assert_eq!(dir.normalize("root.dhall"), Ok("3".to_owned()));
+}
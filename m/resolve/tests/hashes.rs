@@ -0,0 +1,54 @@
+mod common;
+use common::Dir;
+
+const THREE: &str = "sha256:15f52ecf91c94c1baac02d5a4964b2ed8fa401641a2c8a95e8306ec7c1e3b8d2";
+const ZEROS: &str = "sha256:0000000000000000000000000000000000000000000000000000000000000000";
+
+#[test]
+fn matching_hashes_pass() {
+    let root = format!("./three.dhall {}", THREE);
+    let dir = Dir::new("hash-match", &[("root.dhall", &root), ("three.dhall", "3")]);
+    assert_eq!(dir.normalize("root.dhall"), Ok("3".to_owned()));
+}
+
+#[test]
+fn hashes_are_of_the_normal_form() {
+    let root = format!("./three.dhall {}", THREE);
+    let dir = Dir::new("hash-normal", &[("root.dhall", &root), ("three.dhall", "let x = 1 in x + 2 -- three")]);
+    assert_eq!(dir.normalize("root.dhall"), Ok("3".to_owned()));
+}
+
+#[test]
+fn hashes_do_not_depend_on_field_order() {
+    let hash = "sha256:cf9640c81eb68901440211eac8f70ef7af2a58f9d199fa6017bcae10a132f327";
+    let root = format!("[ ./ab.dhall {}, ./ba.dhall {} ]", hash, hash);
+    let dir = Dir::new(
+        "hash-order",
+        &[("root.dhall", &root), ("ab.dhall", "{ a = 2, b = 1 }"), ("ba.dhall", "{ b = 1, a = 2 }")],
+    );
+    assert!(dir.normalize("root.dhall").is_ok(), "{:?}", dir.normalize("root.dhall"));
+}
+
+#[test]
+fn mismatched_hashes_fail() {
+    let root = format!("./three.dhall {}", ZEROS);
+    let dir = Dir::new("hash-mismatch", &[("root.dhall", &root), ("three.dhall", "3")]);
+    let err = dir.resolve("root.dhall").unwrap_err();
+    assert!(err.contains(&format!("hash mismatch for {}", dir.path("three.dhall"))), "{}", err);
+    assert!(err.contains(&format!("found {}", THREE)), "{}", err);
+}
+
+#[test]
+fn mismatched_hashes_fall_back() {
+    let root = format!("./three.dhall {} ? 4", ZEROS);
+    let dir = Dir::new("hash-mismatch-alt", &[("root.dhall", &root), ("three.dhall", "3")]);
+    assert_eq!(dir.normalize("root.dhall"), Ok("4".to_owned()));
+}
+
+#[test]
+fn malformed_hashes_are_parse_errors() {
+    let root = format!("./three.dhall {}", &ZEROS[..ZEROS.len() - 1]);
+    let dir = Dir::new("hash-malformed", &[("root.dhall", &root), ("three.dhall", "3")]);
+    let err = dir.resolve("root.dhall").unwrap_err();
+    assert!(err.contains("parse: invalid sha256 hash"), "{}", err);
+}
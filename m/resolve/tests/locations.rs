@@ -0,0 +1,45 @@
+mod common;
+use common::Dir;
+
+#[test]
+fn type_errors_are_located_in_imported_files() {
+    let dir = Dir::new(
+        "locate-type",
+        &[
+            ("root.dhall", "let a = ./a.dhall\nin  a"),
+            ("a.dhall", "{ x = 1\n, y = ./b.dhall }"),
+            ("b.dhall", "-- b\n  1 + True"),
+        ],
+    );
+    let err = dir.check_error("root.dhall");
+    assert!(err.starts_with(&format!("{}:2:3: type: ", dir.path("b.dhall"))), "{}", err);
+}
+
+#[test]
+fn locations_skip_replaced_imports() {
+    let dir = Dir::new(
+        "locate-after-import",
+        &[
+            (
+                "root.dhall",
+                "{ a = ./a.dhall sha256:0000000000000000000000000000000000000000000000000000000000000000 ? ./b.dhall\n\
+                 , b = ./b.dhall\n\
+                 , c = ./b.dhall + True\n\
+                 }",
+            ),
+            ("b.dhall", "1"),
+        ],
+    );
+    let err = dir.check_error("root.dhall");
+    assert!(err.starts_with(&format!("{}:3:7: type: ", dir.path("root.dhall"))), "{}", err);
+}
+
+#[test]
+fn assertions_are_located_in_imported_files() {
+    let dir = Dir::new(
+        "locate-eval",
+        &[("root.dhall", "[ 1, ./a.dhall ]"), ("a.dhall", "\n\nassert : 1 ≡ 2")],
+    );
+    let err = dir.check_error("root.dhall");
+    assert!(err.starts_with(&format!("{}:3:10: ", dir.path("a.dhall"))), "{}", err);
+}
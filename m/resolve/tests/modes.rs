@@ -0,0 +1,48 @@
+mod common;
+use common::Dir;
+
+#[test]
+fn text_imports_are_text_literals() {
+    let dir = Dir::new(
+        "as-text",
+        &[("root.dhall", "./text.txt as Text"), ("text.txt", "say \"hi\" ${x}\n")],
+    );
+    assert_eq!(dir.normalize("root.dhall"), Ok(r#""say \"hi\" \u0024{x}\n""#.to_owned()));
+}
+
+#[test]
+fn bytes_imports_are_bytes_literals() {
+    let dir = Dir::new("as-bytes", &[("root.dhall", "./bytes.bin as Bytes"), ("bytes.bin", "hi\n")]);
+    assert_eq!(dir.normalize("root.dhall"), Ok("0x\"68690A\"".to_owned()));
+}
+
+#[test]
+fn location_imports_are_not_read() {
+    let dir = Dir::new(
+        "as-location",
+        &[("root.dhall", "[ ./nowhere.dhall as Location, env:HOME as Location, missing as Location ]")],
+    );
+    let normal = dir.normalize("root.dhall").unwrap();
+    assert!(normal.contains(&format!(".Local \"{}\"", dir.path("nowhere.dhall"))), "{}", normal);
+    assert!(normal.contains(".Environment \"HOME\""), "{}", normal);
+    assert!(normal.contains(".Missing"), "{}", normal);
+}
+
+#[test]
+fn env_imports_are_code_or_text() {
+    std::env::set_var("DUST_TEST_MODES", "1 + 1");
+    let dir = Dir::new(
+        "env",
+        &[("root.dhall", "{ code = env:DUST_TEST_MODES, text = env:DUST_TEST_MODES as Text }")],
+    );
+    assert_eq!(dir.normalize("root.dhall"), Ok("{ , code = 2 , text = \"1 + 1\" }".to_owned()));
+}
+
+#[test]
+fn unset_env_imports_fail() {
+    let dir = Dir::new("env-unset", &[("root.dhall", "env:DUST_TEST_UNSET as Text")]);
+    assert!(dir.resolve("root.dhall").is_err());
+
+    let dir = Dir::new("env-unset-alt", &[("root.dhall", "env:DUST_TEST_UNSET as Text ? \"unset\"")]);
+    assert_eq!(dir.normalize("root.dhall"), Ok("\"unset\"".to_owned()));
+}
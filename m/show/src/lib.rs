@@ -52,7 +52,8 @@ impl<'i> fmt::Display for Show<&'i ast::Term1<'i>> {
             ),
             Arrow(None, typ, val) => write!(f, "{} -> {}", Show(typ.as_ref()), Show(val.as_ref())),
             Evaluation(func, t) => write!(f, "{} {}", Show(func.as_ref()), Show(t)),
-            Operation(a, op, b) => write!(f, "{} {} {}", Show(a.as_ref()), op, Show(b.as_ref())),
+            Operation(a, op, b) => write_operation(f, a, op, b),
+            ImportAlt(a, b) => write_operation(f, a, "?", b),
             Ascribe(term, typ) => write!(f, "{} : {}", Show(term.as_ref()), Show(typ.as_ref())),
             With(term, path, val) => write!(
                 f,
@@ -69,6 +70,7 @@ impl<'i> fmt::Display for Show<&'i ast::Term1<'i>> {
                     Show(b.as_ref()),
                 )
             }
+            Assert(typ) => write!(f, "assert : {}", Show(typ.as_ref())),
             Construct(term, data) => {
                 write!(
                     f,
@@ -81,12 +83,137 @@ impl<'i> fmt::Display for Show<&'i ast::Term1<'i>> {
     }
 }
 
+/// Write `a op b`, with the operands in parentheses where needed.
+fn write_operation(f: &mut fmt::Formatter, a: &ast::Term1, op: &str, b: &ast::Term1) -> fmt::Result {
+    use ast::Term1::*;
+
+    let prec = ast::precedence(op);
+    let paren = |t: &ast::Term1, tighter: bool| {
+        let other = match t {
+            Operation(_, op, _) => ast::precedence(op),
+            ImportAlt(_, _) => ast::precedence("?"),
+            Term(_) | Evaluation(_, _) | Construct(_, _) => return false,
+            _ => return true,
+        };
+        if tighter {
+            other <= prec
+        } else {
+            other < prec
+        }
+    };
+    write!(f, "{} {} {}", Operand(a, paren(a, false)), op, Operand(b, paren(b, true)))
+}
+
+impl<'i> fmt::Display for Show<&'i ast::Import<'i>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self(import) = self;
+        write!(f, "{}", Show(&import.target))?;
+        if let Some(hash) = &import.hash {
+            write!(f, " sha256:")?;
+            for byte in hash {
+                write!(f, "{:02x}", byte)?;
+            }
+        }
+        match import.mode {
+            ast::ImportMode::Code => Ok(()),
+            ast::ImportMode::Text => write!(f, " as Text"),
+            ast::ImportMode::Location => write!(f, " as Location"),
+            ast::ImportMode::Bytes => write!(f, " as Bytes"),
+        }
+    }
+}
+
+impl<'i> fmt::Display for Show<&'i ast::ImportTarget<'i>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self(target) = self;
+        use ast::ImportTarget::*;
+        match target {
+            Local(prefix, path) => {
+                write!(f, "{}", prefix.as_str())?;
+                for (i, component) in path.iter().enumerate() {
+                    let separator = if i > 0 { "/" } else { "" };
+                    write!(f, "{}{}", separator, component)?;
+                }
+                Ok(())
+            }
+            Remote(url) => {
+                write!(f, "{}://{}", url.scheme, url.authority)?;
+                for component in &url.path {
+                    write!(f, "/{}", component)?;
+                }
+                if let Some(query) = url.query {
+                    write!(f, "?{}", query)?;
+                }
+                Ok(())
+            }
+            Env(name) => {
+                let mut chars = name.chars();
+                let bash = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                    && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+                if bash {
+                    return write!(f, "env:{}", name);
+                }
+                write!(f, "env:\"")?;
+                for c in name.chars() {
+                    match c {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\x07' => write!(f, "\\a")?,
+                        '\x08' => write!(f, "\\b")?,
+                        '\x0c' => write!(f, "\\f")?,
+                        '\n' => write!(f, "\\n")?,
+                        '\r' => write!(f, "\\r")?,
+                        '\t' => write!(f, "\\t")?,
+                        '\x0b' => write!(f, "\\v")?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            Missing => write!(f, "missing"),
+        }
+    }
+}
+
+struct ShowDouble(f64);
+
+impl fmt::Display for ShowDouble {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let &Self(n) = self;
+        match n {
+            n if n.is_nan() => write!(f, "NaN"),
+            n if n.is_infinite() && n > 0.0 => write!(f, "Infinity"),
+            n if n.is_infinite() => write!(f, "-Infinity"),
+            n if n != 0.0 && (n.abs() >= 1e7 || n.abs() < 0.1) => {
+                let s = format!("{:e}", n);
+                match s.split_once('e') {
+                    Some((m, e)) if !m.contains('.') => write!(f, "{}.0e{}", m, e),
+                    _ => write!(f, "{}", s),
+                }
+            }
+            n => write!(f, "{:?}", n),
+        }
+    }
+}
+
+struct Operand<'a, 'i>(&'a ast::Term1<'i>, bool);
+
+impl<'a, 'i> fmt::Display for Operand<'a, 'i> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self(t, true) => write!(f, "({})", Show(*t)),
+            Self(t, false) => write!(f, "{}", Show(*t)),
+        }
+    }
+}
+
 impl<'i> fmt::Display for Show<&'i ast::Term<'i>> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Self(obj) = self;
         use ast::Term::*;
         match obj {
-            &Integer(v) => write!(f, "{}", v),
+            Natural(v) => write!(f, "{}", v),
+            Integer(v) => write!(f, "{:+}", v),
             Embed(code) => writeln!(f, "{}", code),
             &Var(name, n) => {
                 let ear = if lex::is_keyword(name) { "`" } else { "" };
@@ -99,31 +226,18 @@ impl<'i> fmt::Display for Show<&'i ast::Term<'i>> {
 
                 Ok(())
             }
-            Double(n) => write!(f, "{}", n),
+            Double(n) => write!(f, "{}", ShowDouble(*n)),
+            Bytes(bytes) => {
+                write!(f, "0x\"")?;
+                for byte in bytes {
+                    write!(f, "{:02X}", byte)?;
+                }
+                write!(f, "\"")
+            }
             FieldAccess(term, field) => {
                 write!(f, "{}.{}", Show(term.as_ref()), field)
             }
-            Import {
-                path,
-                as_,
-                guard,
-                fall,
-            } => {
-                write!(f, "{}", path)?;
-                if let Some(guard) = guard {
-                    write!(f, " {}", guard)?;
-                }
-                if let Some(as_) = as_ {
-                    write!(f, " as {}", as_)?;
-                }
-                if let Some((fall, fall_as)) = fall {
-                    write!(f, " ? {}", fall)?;
-                    if let Some(as_) = fall_as {
-                        write!(f, " as {}", as_)?;
-                    }
-                }
-                Ok(())
-            }
+            Import(import) => write!(f, "{}", Show(import.as_ref())),
             Path(path) => print_list(f, SHOW_LIST_STYLE_PATH, path),
             Record(fields) => print_list(f, SHOW_LIST_STYLE_REC, fields),
             TypeRecord(fields) => print_list(f, SHOW_LIST_STYLE_TYPEREC, fields),
@@ -138,6 +252,8 @@ impl<'i> fmt::Display for Show<&'i ast::Term<'i>> {
                     Show(val.as_ref())
                 )
             }
+            ToMap(t) => write!(f, "(toMap {})", Show(t.as_ref())),
+            ShowConstructor(t) => write!(f, "(showConstructor {})", Show(t.as_ref())),
             Text(n, entries) => write!(f, "{}", Show(SText(*n, entries))),
             Project(style, term, names) => {
                 let list_style = match style {
@@ -273,16 +389,24 @@ where
 impl<'i> fmt::Display for Show<SText<'i>> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let SText(style, entries) = self.0;
+        let style = match style {
+            2 if !multi_line(entries) => 1,
+            style => style,
+        };
         let mark = match style {
             1 => "\"",
             _ => "''",
         };
 
         write!(f, "{}", mark)?;
+        if style == 2 {
+            writeln!(f)?;
+        }
 
         for (text, imbue) in entries {
-            if !text.is_empty() {
-                write!(f, "{}", text)?;
+            match style {
+                1 => escape_dquote(f, text)?,
+                _ => escape_ddquote(f, text)?,
             }
             if let Some(val) = imbue {
                 write!(f, "${{ {} }}", Show(val.as_ref()))?;
@@ -295,6 +419,55 @@ impl<'i> fmt::Display for Show<SText<'i>> {
     }
 }
 
+/// Whether the contents can be written back as a multi-line literal:
+/// indented lines would lose their indentation, and a `'` right before the
+/// closing `''` or a hole would be read as an escape.
+fn multi_line(entries: &ast::Deq<ast::TextEntry>) -> bool {
+    let indented = |line: &str| line.starts_with([' ', '\t']);
+    entries.iter().enumerate().all(|(n, (text, _))| {
+        let mut lines = text.split('\n');
+        let first = lines.next().filter(|_| n == 0);
+        !text.ends_with('\'') && !text.contains('\r') && !first.into_iter().chain(lines).any(indented)
+    })
+}
+
+fn escape_dquote(f: &mut fmt::Formatter, text: &str) -> fmt::Result {
+    for c in text.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '$' => write!(f, "\\u0024")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            '\u{8}' => write!(f, "\\b")?,
+            '\u{c}' => write!(f, "\\f")?,
+            c if c < ' ' => write!(f, "\\u{:04X}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    Ok(())
+}
+
+fn escape_ddquote(f: &mut fmt::Formatter, text: &str) -> fmt::Result {
+    let mut rest = text;
+    while let Some(n) = rest.find(['\'', '$']) {
+        write!(f, "{}", &rest[..n])?;
+        rest = &rest[n..];
+        if let Some(after) = rest.strip_prefix("''") {
+            write!(f, "'''")?;
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${") {
+            write!(f, "''${{")?;
+            rest = after;
+        } else {
+            write!(f, "{}", &rest[..1])?;
+            rest = &rest[1..];
+        }
+    }
+    write!(f, "{}", rest)
+}
+
 impl<'i, P, I> fmt::Display for Show<ShowList<'i, P>>
 where
     &'i P: IntoIterator<Item = I>,